@@ -0,0 +1,130 @@
+//! This module contains [`Aov`], the extra images ( arbitrary output variables ) a render can make alongside the normal one.
+//! AOVs split the render into parts for compositing, like the direct and indirect lighting, or hold information about the surfaces the camera sees, like their albedo, normal and depth, which denoisers use to tell edges from noise.
+//!
+//! Every AOV in the camera's `aovs` list is filled in by the same pass over the pixels as the image itself, so they line up exactly, and are gathered with the same filter.
+//! They are stored as layers of the rendered [`Film`](crate::film::Film), [`Film::aov`](crate::film::Film::aov) returns one as its own film, and OpenEXR files include every layer.
+//!
+//! # Example
+//! ```no_run
+//! use rtwlib::{aov::Aov, camera::Camera, hittable::HittableList, image, tonemap::OutputTransform};
+//!
+//! let mut cam = Camera::new();
+//! cam.aovs = vec![Aov::Albedo, Aov::Normal, Aov::Depth];
+//! let film = cam.render_to_film(HittableList::new(), |_| {});
+//!
+//! image::save_film("render.exr", &film, &cam.output_transform()).expect("failed to save"); // every layer in one file
+//! let albedo = film.aov(&Aov::Albedo).unwrap();
+//! image::save_film("albedo.png", &albedo, &OutputTransform::default()).expect("failed to save");
+//! ```
+use std::sync::Arc;
+
+use crate::{camera::Camera, color::Color, hittable::HitRecord, material::Material, vec3::*};
+
+#[derive(Clone, Debug)]
+/// An extra image a render can make, see the module docs.
+/// Everything about surfaces is taken from the first thing each camera ray hits, rays that miss everything leave them at 0.
+pub enum Aov {
+    /// The light emitted by what the camera sees, plus the light that reaches it straight from a light or the sky
+    Direct,
+    /// The light that bounced more than once on the way, so the image is `Direct` plus `Indirect`
+    Indirect,
+    /// The base color of the material, see `Material::albedo`
+    Albedo,
+    /// The shading normal, facing the camera, in world space from -1 to 1
+    Normal,
+    /// The distance from the camera along its view direction, in scene units
+    Depth,
+    /// 1 where the camera sees `material`, and 0 everywhere else, so edges get the share of the pixel covered
+    Mask {
+        /// The name of the layer
+        name: String,
+        /// The material to mask
+        material: Arc<dyn Material>,
+    },
+}
+
+impl Aov {
+    /// Returns the name of the AOV, which is also its layer name in OpenEXR files.
+    pub fn name(&self) -> &str {
+        match self {
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Mask { name, .. } => name,
+        }
+    }
+    /// Returns the built-in AOV called `name`, or `None` if there isn't one. Masks aren't built in, since they need a material.
+    pub fn builtin(name: &str) -> Option<Aov> {
+        match name {
+            "direct" => Some(Aov::Direct),
+            "indirect" => Some(Aov::Indirect),
+            "albedo" => Some(Aov::Albedo),
+            "normal" => Some(Aov::Normal),
+            "depth" => Some(Aov::Depth),
+            _ => None,
+        }
+    }
+    /// Returns the channels the AOV is saved with in OpenEXR files. Colors are RGB, normals are XYZ, depth is a single Z channel, and masks are a single alpha channel.
+    /// Single channel AOVs are stored as gray colors in films, so they can be viewed like the others.
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            Aov::Direct | Aov::Indirect | Aov::Albedo => &["R", "G", "B"],
+            Aov::Normal => &["X", "Y", "Z"],
+            Aov::Depth => &["Z"],
+            Aov::Mask { .. } => &["A"],
+        }
+    }
+    /// Returns the value of the AOV for one sample.
+    pub fn value(&self, sample: &AovSample) -> Color {
+        match self {
+            Aov::Direct => sample.direct,
+            Aov::Indirect => sample.indirect,
+            Aov::Albedo => sample.albedo,
+            Aov::Normal => sample.normal,
+            Aov::Depth => Color::from(sample.depth),
+            Aov::Mask { material, .. } => {
+                // compares the addresses only, the vtable part of the pointer can differ for the same material
+                let hit = sample.material.as_ref().is_some_and(|hit| {
+                    Arc::as_ptr(hit) as *const () == Arc::as_ptr(material) as *const ()
+                });
+                Color::from(if hit { 1. } else { 0. })
+            }
+        }
+    }
+}
+
+/// AOVs are told apart by name, so two layers can't have the same name.
+impl PartialEq for Aov {
+    fn eq(&self, other: &Aov) -> bool {
+        self.name() == other.name()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// What a single camera ray found, filled in by the integrator, and turned into the values of each AOV.
+pub struct AovSample {
+    /// The direct part of the light, see [`Aov::Direct`]
+    pub direct: Color,
+    /// The indirect part of the light, see [`Aov::Indirect`]
+    pub indirect: Color,
+    /// The albedo of the first hit
+    pub albedo: Color,
+    /// The shading normal of the first hit
+    pub normal: Vec3,
+    /// The depth of the first hit
+    pub depth: f64,
+    /// The material of the first hit, or `None` if the ray missed everything
+    pub material: Option<Arc<dyn Material>>,
+}
+
+impl AovSample {
+    /// Fills in the albedo, normal, depth and material from the first hit of a camera ray.
+    pub fn record_hit(&mut self, rec: &HitRecord, camera: &Camera) {
+        self.albedo = rec.mat.albedo(rec);
+        self.normal = rec.normal;
+        self.depth = camera.depth(rec.p);
+        self.material = Some(Arc::clone(&rec.mat));
+    }
+}
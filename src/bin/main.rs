@@ -1,80 +1,596 @@
-use plane::Plane;
-use rtwlib::camera::*;
-use rtwlib::color::Color;
-use rtwlib::hittable::*;
-use rtwlib::material::*;
-//use rand::Rng;
-use rtwlib::hittable::sphere::*;
-use rtwlib::vec3::*;
-use std::rc::Rc;
-use std::{fs::File, io::Write};
-fn main() -> std::io::Result<()> {
-    //Create a world
-    let mut world = HittableList {
-        objects: Vec::new(),
+//! `rtw`, a command line renderer for rtwlib scene files.
+//! Run `rtw --help` for usage.
+use rtwlib::{
+    aov::Aov,
+    camera::Camera,
+    checkpoint::Checkpoint,
+    denoise::Denoiser,
+    film::{Film, Filter},
+    hittable::HittableList,
+    image::ImageFormat,
+    integrator::DebugMode,
+    progressive::ProgressiveRenderer,
+    sampler::SamplerKind,
+    scene::{load_scene, IntegratorDescription},
+    tile::Tile,
+    tonemap::{OutputTransform, ToneMap},
+};
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+const USAGE: &str = "\
+Usage: rtw <SCENE> <OUTPUT> [OPTIONS]
+
+Renders a JSON scene file to an image, the format is picked from the output extension.
+Supported formats: .png, .ppm, and .exr, .hdr, .pfm for HDR output
+
+Options:
+  -W, --width <N>      Image width in pixels, overrides the scene
+  -H, --height <N>     Image height in pixels, overrides the scene
+  -s, --samples <N>    Samples per pixel, overrides the scene
+  -b, --bounces <N>    Maximum bounces per ray, overrides the scene
+      --adaptive <NOISE>
+                       Stop sampling pixels once their noise is below this,
+                       0.01 is a good start, overrides the scene
+      --min-samples <N>
+                       Samples every pixel gets before --adaptive can stop it
+      --heatmap <FILE> Also save an image of the samples used in each pixel
+      --aov <LIST>     Also render these extra images, separated by commas:
+                       direct, indirect, albedo, normal or depth. They are
+                       layers of .exr outputs, or saved next to the output
+                       as OUTPUT.NAME.EXT for other formats
+      --denoise        Denoise the image, guided by the albedo, normal and
+                       depth, which are also added to .exr outputs
+  -t, --threads <N>    Number of render threads, defaults to every core
+      --time <SECONDS> Render for this long instead, stopping early if the
+                       sample count is reached
+      --checkpoint <FILE>
+                       Save the render to FILE as it goes, and resume from it
+                       if it's already there, even with more samples
+      --checkpoint-every <SECONDS>
+                       How often to save the checkpoint, defaults to 60
+      --region <X0,Y0,X1,Y1>
+                       Only render the pixels from X0,Y0 up to X1,Y1, the
+                       output image is the size of the region
+      --integrator <NAME>
+                       Light transport algorithm, overrides the scene:
+                       path, or ao for ambient occlusion, or a debug view:
+                       normal, depth, albedo, object-id, material-id or uv
+      --seed <N>       Seed for the random numbers, overrides the scene
+      --sampler <NAME> Sampler for the random numbers, overrides the scene:
+                       random, stratified, halton, sobol or blue-noise
+  -e, --exposure <EV>  Exposure in stops, overrides the scene
+      --tone-map <OP>  Tone mapping operator, overrides the scene:
+                       none, reinhard, aces, hable or agx
+      --filter <NAME>  Pixel filter, overrides the scene:
+                       box, tent, gaussian, mitchell or lanczos
+  -q, --quiet          Don't show a progress bar
+      --help           Show this message";
+
+/// The settings picked on the command line.
+struct Options {
+    scene: PathBuf,
+    output: PathBuf,
+    width: Option<u32>,
+    height: Option<u32>,
+    samples: Option<u32>,
+    bounces: Option<u32>,
+    noise_threshold: Option<f64>,
+    min_samples: Option<u32>,
+    heatmap: Option<PathBuf>,
+    aovs: Vec<Aov>,
+    denoise: bool,
+    threads: Option<usize>,
+    time: Option<Duration>,
+    checkpoint: Option<PathBuf>,
+    checkpoint_interval: Duration,
+    region: Option<Tile>,
+    integrator: Option<IntegratorDescription>,
+    seed: Option<u64>,
+    sampler: Option<SamplerKind>,
+    exposure: Option<f64>,
+    tone_map: Option<ToneMap>,
+    filter: Option<Filter>,
+    quiet: bool,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut positional = Vec::new();
+    let mut options = Options {
+        scene: PathBuf::new(),
+        output: PathBuf::new(),
+        width: None,
+        height: None,
+        samples: None,
+        bounces: None,
+        noise_threshold: None,
+        min_samples: None,
+        heatmap: None,
+        aovs: Vec::new(),
+        denoise: false,
+        threads: None,
+        time: None,
+        checkpoint: None,
+        checkpoint_interval: Duration::from_secs(60),
+        region: None,
+        integrator: None,
+        seed: None,
+        sampler: None,
+        exposure: None,
+        tone_map: None,
+        filter: None,
+        quiet: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("{} needs a value", name))?
+                .parse()
+                .map_err(|_| format!("{} needs a positive whole number", name))
+        };
+        match arg.as_str() {
+            "-W" | "--width" => options.width = Some(value(arg)?),
+            "-H" | "--height" => options.height = Some(value(arg)?),
+            "-s" | "--samples" => options.samples = Some(value(arg)?),
+            "-b" | "--bounces" => options.bounces = Some(value(arg)?),
+            "-t" | "--threads" => options.threads = Some(value(arg)? as usize),
+            "--min-samples" => options.min_samples = Some(value(arg)?),
+            "--adaptive" => {
+                let noise = args.next().ok_or("--adaptive needs a value")?;
+                options.noise_threshold = Some(
+                    noise
+                        .parse()
+                        .ok()
+                        .filter(|noise: &f64| *noise >= 0.0)
+                        .ok_or_else(|| format!("invalid noise threshold '{}'", noise))?,
+                );
+            }
+            "--heatmap" => {
+                let path = args.next().ok_or("--heatmap needs a value")?;
+                options.heatmap = Some(PathBuf::from(path));
+            }
+            "--aov" => {
+                let list = args.next().ok_or("--aov needs a value")?;
+                options.aovs = parse_aovs(list)?;
+            }
+            "--time" => {
+                let time = args.next().ok_or("--time needs a value")?;
+                options.time = Some(
+                    time.parse()
+                        .ok()
+                        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                        .filter(|time| !time.is_zero())
+                        .ok_or_else(|| format!("invalid time '{}'", time))?,
+                );
+            }
+            "--checkpoint" => {
+                let path = args.next().ok_or("--checkpoint needs a value")?;
+                options.checkpoint = Some(PathBuf::from(path));
+            }
+            "--checkpoint-every" => {
+                let interval = args.next().ok_or("--checkpoint-every needs a value")?;
+                options.checkpoint_interval = interval
+                    .parse()
+                    .ok()
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .ok_or_else(|| format!("invalid interval '{}'", interval))?;
+            }
+            "--region" => {
+                let region = args.next().ok_or("--region needs a value")?;
+                options.region = Some(parse_region(region)?);
+            }
+            "--integrator" => {
+                let name = args.next().ok_or("--integrator needs a value")?;
+                options.integrator = Some(parse_integrator(name)?);
+            }
+            "--seed" => {
+                let seed = args.next().ok_or("--seed needs a value")?;
+                options.seed = Some(
+                    seed.parse()
+                        .map_err(|_| format!("invalid seed '{}'", seed))?,
+                );
+            }
+            "--sampler" => {
+                let name = args.next().ok_or("--sampler needs a value")?;
+                options.sampler = Some(parse_sampler(name)?);
+            }
+            "-e" | "--exposure" => {
+                let exposure = args.next().ok_or("--exposure needs a value")?;
+                options.exposure = Some(
+                    exposure
+                        .parse()
+                        .map_err(|_| format!("invalid exposure '{}'", exposure))?,
+                );
+            }
+            "--tone-map" => {
+                let name = args.next().ok_or("--tone-map needs a value")?;
+                options.tone_map = Some(parse_tone_map(name)?);
+            }
+            "--filter" => {
+                let name = args.next().ok_or("--filter needs a value")?;
+                options.filter = Some(parse_filter(name)?);
+            }
+            "--denoise" => options.denoise = true,
+            "-q" | "--quiet" => options.quiet = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+
+    match <[PathBuf; 2]>::try_from(positional) {
+        Ok([scene, output]) => {
+            options.scene = scene;
+            options.output = output;
+        }
+        Err(_) => return Err("expected a scene file and an output file".to_string()),
+    }
+    if options.width == Some(0) || options.height == Some(0) || options.samples == Some(0) {
+        return Err("width, height and samples must be above 0".to_string());
+    }
+    if options.region.is_some() && options.time.is_some() {
+        return Err("--region and --time can't be used together".to_string());
+    }
+    if options.checkpoint.is_some() && (options.region.is_some() || options.time.is_some()) {
+        return Err("--checkpoint can't be used with --region or --time".to_string());
+    }
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    // check the format before rendering, so a typo doesn't cost a whole render
+    let format = output_format(&options.output)?;
+    let heatmap_format = options.heatmap.as_deref().map(output_format).transpose()?;
+    let (mut cam, world) = load_scene(&options.scene)
+        .map_err(|err| format!("failed to load {}: {}", options.scene.display(), err))?;
+
+    cam.image_width = options.width.unwrap_or(cam.image_width);
+    cam.image_height = options.height.unwrap_or(cam.image_height);
+    cam.samples = options.samples.unwrap_or(cam.samples);
+    cam.bounces = options.bounces.unwrap_or(cam.bounces);
+    cam.noise_threshold = options.noise_threshold.unwrap_or(cam.noise_threshold);
+    cam.min_samples = options.min_samples.unwrap_or(cam.min_samples);
+    cam.threads = options.threads.unwrap_or(cam.threads);
+    if let Some(integrator) = &options.integrator {
+        cam.integrator = integrator.build();
+    }
+    cam.seed = options.seed.unwrap_or(cam.seed);
+    cam.sampler = options.sampler.unwrap_or(cam.sampler);
+    cam.exposure = options.exposure.unwrap_or(cam.exposure);
+    cam.tone_map = options.tone_map.unwrap_or(cam.tone_map);
+    cam.filter = options.filter.unwrap_or(cam.filter);
+    cam.aovs = options.aovs.clone();
+    if options.denoise {
+        for guide in [Aov::Albedo, Aov::Normal, Aov::Depth] {
+            if !cam.aovs.contains(&guide) {
+                cam.aovs.push(guide);
+            }
+        }
+    }
+    cam.initialize();
+    let output = cam.output_transform();
+
+    let film = if let Some(path) = &options.checkpoint {
+        render_with_checkpoints(cam, world, path, options)?
+    } else {
+        match options.time {
+            Some(budget) => {
+                if !options.quiet {
+                    eprintln!("Rendering for {:.1}s", budget.as_secs_f64());
+                }
+                cam.render_for(world, budget)
+            }
+            None => {
+                let image = Tile::new(0, 0, cam.image_width, cam.image_height);
+                let region = options.region.unwrap_or(image);
+                if region.is_empty() || region.intersect(image) != region {
+                    return Err(format!(
+                        "the region doesn't fit in the {}x{} image",
+                        cam.image_width, cam.image_height
+                    )
+                    .into());
+                }
+                let tiles = cam.region_tiles(region).len() as u32;
+                let mut progress = ProgressBar::new(tiles, "tiles", options.quiet);
+                let film = cam.render_region(&world, region, |tile| progress.update(tile + 1));
+                progress.finish();
+                film
+            }
+        }
     };
-    //create the materials
-    let mat_r = Rc::new(Lambertian::new(Color::new(1, 0, 0)));
-    let mat_o = Rc::new(Lambertian::new(Color::new(1., 0.5, 0.)));
-    let mat_y = Rc::new(Lambertian::new(Color::new(1., 1., 0.)));
-    let mat_g = Rc::new(Lambertian::new(Color::new(0, 1, 0)));
-    let mat_b = Rc::new(Lambertian::new(Color::new(0, 0, 1)));
-    let mat_v = Rc::new(Lambertian::new(Color::new(0.8, 0.3, 0.8)));
-    let mat_ground = Rc::new(Lambertian::new(Color::new(0.9, 0.9, 0.9)));
-    //add spheres to the world
-    world.add(Sphere::new(Point3::new(0., 0., 2.5), 0.5, mat_r));
-    world.add(Sphere::new(Point3::new(0., 0., 1.5), 0.5, mat_o));
-    world.add(Sphere::new(Point3::new(0., 0., 0.5), 0.5, mat_y));
-    world.add(Sphere::new(Point3::new(0., 0., -0.5), 0.5, mat_g));
-    world.add(Sphere::new(Point3::new(0., 0., -1.5), 0.5, mat_b));
-    world.add(Sphere::new(Point3::new(0., 0., -2.5), 0.5, mat_v));
-
-    world.add(Plane::new(
-        Point3::new(0., -0.5, 0.),
-        Vec3::new(0., 1., 0.),
-        mat_ground,
-    ));
-    //Creates a file based on args
-    let args: Vec<String> = std::env::args().collect();
-    let mut file = File::create(args[1].to_string())?;
-
-    //create a new cam, and set relavant settings.
-    let mut cam = Camera::new();
-    cam.image_width = 1600;
-    cam.image_height = 900;
-    cam.samples = 250;
-    cam.bounces = 50;
-
-    cam.vfov = 60.0;
-    cam.lookfrom = Point3::new(3., 1.0, 0.);
-    cam.lookat = Point3::new(0., 0.0, 0.);
-    cam.vup = Vec3::new(0., 1., 0.);
-
-    cam.defocus_angle = 0.0;
-    cam.focus_dist = 2.3;
-    cam.initialize(); //make sure to do this before render
-    let lines = cam.image_height;
-
-    //renders the image to a vector of bytes, running the progress callback every line.
-    let buffer = cam.render_to_bytes(world, |progress| update_progress(progress, lines));
-
-    println!("\n\rDone!");
-    println!("Writing to {}...", args[1].to_string());
-
-    //writes the buffer to a ppm
-    file.write(format!("P6\n{} {}\n255\n", cam.image_width, cam.get_height()).as_bytes())?;
-    file.write_all(&buffer)?;
+
+    let film = if options.denoise {
+        Denoiser::new().denoise(&film)
+    } else {
+        film
+    };
+
+    save(&options.output, format, &film, &output, options.quiet)?;
+    // exr files hold the aovs as layers, other formats get a file for each one
+    if !matches!(format, ImageFormat::Exr(_)) {
+        for aov in &options.aovs {
+            let Some(layer) = film.aov(aov) else {
+                continue;
+            };
+            // only the lighting is tone mapped, the other aovs are data
+            let output = match aov {
+                Aov::Direct | Aov::Indirect => output,
+                _ => OutputTransform::default(),
+            };
+            save(
+                &aov_path(&options.output, aov),
+                format,
+                &layer,
+                &output,
+                options.quiet,
+            )?;
+        }
+    }
+    if let (Some(path), Some(format)) = (&options.heatmap, heatmap_format) {
+        let heatmap = film.sample_heatmap();
+        save(
+            path,
+            format,
+            &heatmap,
+            &OutputTransform::default(),
+            options.quiet,
+        )?;
+    }
     Ok(())
 }
 
-// Simple progress callback function
-fn update_progress(progress: u32, lines: u32) {
-    let strwidth = lines.to_string().len();
-    print!(
-        "\r{:0width$}/{} lines rendered",
-        progress,
-        lines,
-        width = strwidth
+fn save(
+    path: &Path,
+    format: ImageFormat,
+    film: &Film,
+    output: &OutputTransform,
+    quiet: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut file = BufWriter::new(
+        File::create(path)
+            .map_err(|err| format!("failed to create {}: {}", path.display(), err))?,
     );
-    std::io::stdout().flush().unwrap();
+    format.write_film(&mut file, film, output)?;
+    file.flush()?;
+    if !quiet {
+        eprintln!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Returns the path an AOV is saved to, `render.png` becomes `render.albedo.png`.
+fn aov_path(output: &Path, aov: &Aov) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = output.extension().unwrap_or_default().to_string_lossy();
+    output.with_file_name(format!("{}.{}.{}", stem, aov.name(), extension))
+}
+
+/// Parses a list of AOVs, like `albedo,normal`.
+fn parse_aovs(list: &str) -> Result<Vec<Aov>, String> {
+    list.split(',')
+        .map(|name| Aov::builtin(name.trim()).ok_or_else(|| format!("unknown aov '{}'", name)))
+        .collect()
+}
+
+fn parse_tone_map(name: &str) -> Result<ToneMap, String> {
+    match name {
+        "none" => Ok(ToneMap::None),
+        "reinhard" => Ok(ToneMap::Reinhard),
+        "aces" => Ok(ToneMap::Aces),
+        "hable" => Ok(ToneMap::Hable),
+        "agx" => Ok(ToneMap::Agx),
+        _ => Err(format!("unknown tone mapping operator '{}'", name)),
+    }
+}
+
+fn parse_sampler(name: &str) -> Result<SamplerKind, String> {
+    match name {
+        "random" => Ok(SamplerKind::Random),
+        "stratified" => Ok(SamplerKind::Stratified),
+        "halton" => Ok(SamplerKind::Halton),
+        "sobol" => Ok(SamplerKind::Sobol),
+        "blue-noise" => Ok(SamplerKind::BlueNoise),
+        _ => Err(format!("unknown sampler '{}'", name)),
+    }
+}
+
+fn parse_integrator(name: &str) -> Result<IntegratorDescription, String> {
+    let debug = |mode| IntegratorDescription::Debug { mode };
+    match name {
+        "path" => Ok(IntegratorDescription::Path),
+        "ao" => Ok(IntegratorDescription::AmbientOcclusion { distance: 1.0 }),
+        "normal" => Ok(debug(DebugMode::Normal)),
+        "depth" => Ok(debug(DebugMode::Depth)),
+        "albedo" => Ok(debug(DebugMode::Albedo)),
+        "object-id" => Ok(debug(DebugMode::ObjectId)),
+        "material-id" => Ok(debug(DebugMode::MaterialId)),
+        "uv" => Ok(debug(DebugMode::Uv)),
+        _ => Err(format!("unknown integrator '{}'", name)),
+    }
+}
+
+/// Parses a region from its corners, like `10,20,110,120`.
+fn parse_region(text: &str) -> Result<Tile, String> {
+    let corners: Vec<u32> = text
+        .split(',')
+        .map(|n| n.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid region '{}'", text))?;
+    match corners[..] {
+        [x0, y0, x1, y1] if x0 < x1 && y0 < y1 => Ok(Tile::from_corners(x0, y0, x1, y1)),
+        _ => Err(format!(
+            "invalid region '{}', expected X0,Y0,X1,Y1 with X0 < X1 and Y0 < Y1",
+            text
+        )),
+    }
+}
+
+/// Picks a filter by name, with the radius recommended for each one.
+fn parse_filter(name: &str) -> Result<Filter, String> {
+    match name {
+        "box" => Ok(Filter::Box { radius: 0.5 }),
+        "tent" => Ok(Filter::Tent { radius: 1.0 }),
+        "gaussian" => Ok(Filter::Gaussian { radius: 1.5 }),
+        "mitchell" => Ok(Filter::Mitchell { radius: 2.0 }),
+        "lanczos" => Ok(Filter::Lanczos { radius: 3.0 }),
+        _ => Err(format!("unknown filter '{}'", name)),
+    }
+}
+
+fn output_format(path: &Path) -> Result<ImageFormat, String> {
+    ImageFormat::from_path(path).ok_or_else(|| match path.extension() {
+        Some(ext) => format!("unsupported output format '.{}'", ext.to_string_lossy()),
+        None => "the output file needs an extension to pick a format".to_string(),
+    })
+}
+
+/// A progress bar on stderr, showing the tiles rendered, and an estimate of the time left.
+struct ProgressBar {
+    total: u32,
+    /// What's being counted, like tiles or samples
+    unit: &'static str,
+    /// How much was already done before the bar started, which doesn't count towards the ETA
+    first: u32,
+    start: Instant,
+    hidden: bool,
+}
+
+impl ProgressBar {
+    const WIDTH: u32 = 30;
+
+    fn new(total: u32, unit: &'static str, hidden: bool) -> Self {
+        ProgressBar {
+            total,
+            unit,
+            first: 0,
+            start: Instant::now(),
+            hidden,
+        }
+    }
+
+    fn starting_at(self, first: u32) -> Self {
+        ProgressBar { first, ..self }
+    }
+
+    fn update(&mut self, done: u32) {
+        if self.hidden {
+            return;
+        }
+        let elapsed = self.start.elapsed();
+        let done = done.min(self.total);
+        let filled = (done * Self::WIDTH / self.total.max(1)) as usize;
+        let eta = if done <= self.first {
+            "--".to_string()
+        } else {
+            format_duration(
+                elapsed.mul_f64((self.total - done) as f64 / (done - self.first) as f64),
+            )
+        };
+        eprint!(
+            "\r[{}{}] {:3}% {}/{} {}, elapsed {}, ETA {}  ",
+            "#".repeat(filled),
+            ".".repeat(Self::WIDTH as usize - filled),
+            done * 100 / self.total.max(1),
+            done,
+            self.total,
+            self.unit,
+            format_duration(elapsed),
+            eta
+        );
+        // progress is only cosmetic, so a closed stderr shouldn't stop the render
+        std::io::stderr().flush().ok();
+    }
+
+    fn finish(&self) {
+        if !self.hidden {
+            eprintln!("\nDone in {}", format_duration(self.start.elapsed()));
+        }
+    }
+}
+
+/// Renders in passes of one sample, saving a checkpoint to `path` every so often, and once more at the end.
+/// If there's already a checkpoint there, the render carries on from it.
+fn render_with_checkpoints(
+    cam: Camera,
+    world: HittableList,
+    path: &Path,
+    options: &Options,
+) -> Result<Film, Box<dyn Error>> {
+    let mut renderer = if path.exists() {
+        let checkpoint = Checkpoint::load(path, &cam.aovs)
+            .map_err(|err| format!("failed to load {}: {}", path.display(), err))?;
+        ProgressiveRenderer::resume(cam, world, checkpoint).map_err(|err| {
+            format!(
+                "can't resume from {}: {}, delete it to start over",
+                path.display(),
+                err
+            )
+        })?
+    } else {
+        ProgressiveRenderer::new(cam, world)
+    };
+    if !options.quiet && renderer.samples() > 0 {
+        eprintln!("Resuming from {} samples", renderer.samples());
+    }
+
+    let save = |renderer: &ProgressiveRenderer| {
+        renderer
+            .checkpoint()
+            .save(path)
+            .map_err(|err| format!("failed to save {}: {}", path.display(), err))
+    };
+    let mut progress = ProgressBar::new(renderer.camera().samples, "samples", options.quiet)
+        .starting_at(renderer.samples());
+    let mut saved = Instant::now();
+    while !renderer.is_done() {
+        renderer.render_pass(1);
+        progress.update(renderer.samples());
+        if saved.elapsed() >= options.checkpoint_interval {
+            save(&renderer)?;
+            saved = Instant::now();
+        }
+    }
+    save(&renderer)?;
+    progress.finish();
+    Ok(renderer.film().clone())
+}
+
+/// Formats a duration as `1h02m03s`, `2m03s` or `3s`.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, s) => format!("{}h{:02}m{:02}s", h, m, s),
+    }
 }
@@ -2,23 +2,80 @@
 //! The functions are here to provide a simple way to render a scene, but feel free to implement your own methods on the `Camera` struct to render to different outputs.
 //!
 //! # Example
-//! To render a scene, you can simply use one of the premade renderers, like `render_to_bytes` or `render_to_string`, these have a callback function that is called every line, which can be used to show progress.
-//! ```
+//! To render a scene, you can simply use one of the premade renderers, like `render_to_bytes`, `render_to_string` or `render_to_film`, these have a callback function that is called every time a tile is finished, which can be used to show progress.
+//! ```no_run
 //! use rtwlib::{camera::Camera, hittable::*, material::*, vec3::*};
 //!
+//! let world = HittableList::new();
+//! let mut camera = Camera::new();
 //! camera.render_to_bytes(world, |progress| println!("Progress: {}%", progress));
 //! ```
 //!
-//! If you want more control over your renders, the `get_ray` and `ray_color` functions are the backbone of the rendering process, and can be used to create your own rendering functions.
+//! The premade renderers split the image into [`Tile`]s of `tile_size` pixels, and render them on `threads` worker threads. Finished tiles are collected on the calling thread, which is also where the progress callback runs.
+//! To render only part of the image, use `render_region`, which gives exactly the same pixels as the full render.
+//!
+//! The callback can stop a render early by returning [`RenderControl::Stop`], the tiles that are already finished are kept. To render for a set amount of time instead of a set number of samples, use `render_for`.
+//! ```no_run
+//! use rtwlib::{camera::{Camera, RenderControl}, hittable::HittableList};
+//! use std::{sync::atomic::{AtomicBool, Ordering}, time::Duration};
+//!
+//! let mut camera = Camera::new();
+//! // set from somewhere else, like a cancel button on another thread
+//! let cancelled = AtomicBool::new(false);
+//! let image = camera.render_to_bytes(HittableList::new(), |_| {
+//!     if cancelled.load(Ordering::Relaxed) {
+//!         RenderControl::Stop
+//!     } else {
+//!         RenderControl::Continue
+//!     }
+//! });
+//! // the best image in 5 seconds
+//! let film = camera.render_for(HittableList::new(), Duration::from_secs(5));
+//! ```
+//!
+//! If you want more control over your renders, the `get_ray` function and the camera's `integrator` are the backbone of the rendering process, and can be used to create your own rendering functions.
 //! When rendering manually ( without a premade function ) the generall process is as follows:
 //!     1. Initalize the camera for rendering using `initialize`.
-//!     2. Create a `Ray` using `get_ray` with the pixel coordinates.
-//!     3. Trace the ray using `ray_color` to get the color of the pixel.
+//!     2. Create a `Ray` using `get_ray` with the pixel coordinates, and a [`Sampler`] for the random numbers.
+//!     3. Trace the ray using `integrator.radiance` ( or `ray_color`, which always path traces ) to get the color of the pixel.
+//!
+//! The premade renderers restart their sampler for every sample of every pixel, from the camera's `seed`, so the same seed always gives the same image.
+//! ```
+//! use rtwlib::{camera::Camera, hittable::HittableList};
+//!
+//! let mut camera = Camera::new();
+//! (camera.image_width, camera.image_height, camera.samples) = (16, 16, 4);
+//! camera.seed = 42;
+//! let first = camera.render_to_bytes(HittableList::new(), |_| {});
+//! let second = camera.render_to_bytes(HittableList::new(), |_| {});
+//! assert_eq!(first, second);
+//! ```
 //! From here, you can do whatever you want with the color, save it to a buffer, write it to a file, or even display it immediately on screen.
 //!
 //! Cameras can have
-use crate::{color::*, hittable::*, ray::*, vec3::*};
-use rand::{thread_rng, Rng};
+use crate::{
+    aov::{Aov, AovSample},
+    color::*,
+    film::{Film, Filter, PixelStats},
+    hittable::*,
+    integrator::{Integrator, PathIntegrator},
+    ray::*,
+    sampler::{Sampler, SamplerKind},
+    scene::SkyDescription,
+    tile::{Tile, Tiles},
+    tonemap::{OutputTransform, ToneMap},
+    vec3::*,
+};
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 /// #Camera
 ///
@@ -41,6 +98,18 @@ use rand::{thread_rng, Rng};
 /// * `defocus_angle` - The angle of the defocus disk, used to set blur strength. 0.0 disables distance blur effect.
 /// * `focus_dist` - The distance from the camera to the focus plane.
 /// * `sky` - The sky object, used to render the background of the scene.
+/// * `integrator` - The algorithm that works out the color of each camera ray, see the [`integrator`](crate::integrator) module. Defaults to a path tracer.
+/// * `aovs` - The extra images to render alongside the normal one, as layers of the [`Film`], see the [`aov`](crate::aov) module. Defaults to none.
+/// * `threads` - The number of threads used by the premade render functions, defaults to the number of available cores.
+/// * `tile_size` - The width and height of the tiles the image is split into for rendering, in pixels. Defaults to 32.
+/// * `exposure` - The exposure of the render in stops, applied before tone mapping. Each stop doubles the brightness.
+/// * `tone_map` - The tone mapping operator, used to fit bright colors into the range of the output. Defaults to none, which clips them.
+/// * `transfer` - The curve used to encode colors into bytes, defaults to sRGB.
+/// * `filter` - The pixel reconstruction filter, used to blend samples into pixels. Defaults to a box filter, which averages the samples in each pixel.
+/// * `seed` - The seed for the random numbers used while rendering. Renders with the same seed and settings are identical.
+/// * `sampler` - The kind of sampler used for the random numbers. Defaults to Sobol, which converges faster than plain random numbers.
+/// * `noise_threshold` - Turns on adaptive sampling when above 0.0: pixels stop getting samples once their noise ( see `Film::noise` ) is below the threshold. 0.01 is a good start, lower is cleaner but slower.
+/// * `min_samples` - With adaptive sampling, the number of samples every pixel gets before it can stop, so pixels aren't stopped by a few lucky samples. Defaults to 16.
 ///
 /// Private: (used for internal rendering calculations)
 /// * `sample_scale` - The scale of the samples, calculated as `1.0 / samples as f64`.
@@ -89,6 +158,30 @@ pub struct Camera {
     defocus_disc_v: Vec3,
     /// The sky object, used to render the background of the scene
     pub sky: Box<dyn Sky>,
+    /// The light transport algorithm, see [`Integrator`]
+    pub integrator: Box<dyn Integrator>,
+    /// The extra images rendered alongside the normal one, see [`Aov`]
+    pub aovs: Vec<Aov>,
+    /// The number of threads used to render, 1 renders on the calling thread only.
+    pub threads: usize,
+    /// The size of the tiles the image is split into for rendering
+    pub tile_size: u32,
+    /// The exposure in stops, 0.0 leaves the render as it is
+    pub exposure: f64,
+    /// The tone mapping operator, see [`ToneMap`]
+    pub tone_map: ToneMap,
+    /// The curve used to encode the linear render into bytes, see [`TransferFunction`].
+    pub transfer: TransferFunction,
+    /// The pixel reconstruction filter, see [`Filter`]
+    pub filter: Filter,
+    /// The seed for the random numbers used while rendering
+    pub seed: u64,
+    /// The kind of sampler used while rendering, see [`SamplerKind`]
+    pub sampler: SamplerKind,
+    /// The noise level where pixels stop getting samples, 0.0 turns adaptive sampling off
+    pub noise_threshold: f64,
+    /// The number of samples every pixel gets before adaptive sampling can stop it
+    pub min_samples: u32,
 }
 
 impl Camera {
@@ -98,9 +191,9 @@ impl Camera {
         cam.initialize();
         cam
     }
-    /// Renders the scene to a string, runs progress callback every line
+    /// Renders the scene to a string, runs progress callback every tile
     /// * `world` - The HittableList representing the scene
-    /// * `progress` - A callback function with u32 parameter.
+    /// * `progress` - A callback function with u32 parameter, which can return [`RenderControl::Stop`] to stop the render early.
     ///
     /// Returns a `String` containing the rgb values for each pixel, each value seperated by a space, and each pixel seperated by a newline.
     ///
@@ -108,56 +201,271 @@ impl Camera {
     /// R G B\n
     /// ...`
 
-    pub fn render_to_string<F>(&mut self, world: HittableList, mut progress: F) -> String
+    pub fn render_to_string<F, R>(&mut self, world: HittableList, progress: F) -> String
     where
-        F: FnMut(u32),
+        F: FnMut(u32) -> R,
+        R: Into<RenderControl>,
     {
         self.initialize();
         let mut buffer =
             String::with_capacity((self.image_width * self.image_height * 12) as usize);
 
-        for j in 0..self.image_height {
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::from(0.0);
-
-                for _ in 0..self.samples {
-                    let r = self.get_ray(i, j);
-                    pixel_color += self.ray_color(r, self.bounces, &world);
-                }
-                let rgb = (pixel_color * self.sample_scale).to_rgb_bytes();
-                buffer.push_str(&format!("{} {} {}\n", rgb[0], rgb[1], rgb[2]));
-            }
-            progress(j);
+        let output = self.output_transform();
+        for pixel_color in self
+            .render_film(&world, self.image_tile(), 0..self.samples, None, progress)
+            .pixels()
+        {
+            let rgb = output.to_rgb_bytes(pixel_color);
+            buffer.push_str(&format!("{} {} {}\n", rgb[0], rgb[1], rgb[2]));
         }
         buffer
     }
 
-    /// Renders the scene to a buffer of bytes, runs progress callback every line
+    /// Renders the scene to a buffer of bytes, runs progress callback every tile
     /// * `world` - The HittableList representing the scene
-    /// * `progress` - A callback function with u32 parameter.
+    /// * `progress` - A callback function with u32 parameter, which can return [`RenderControl::Stop`] to stop the render early.
     ///
     /// Returns a `Vec<u8>`, where every three bytes represent the RGB values of a pixel.
     /// `[R, G, B, R, G, B, ...]`
-    pub fn render_to_bytes<F>(&mut self, world: HittableList, mut progress: F) -> Vec<u8>
+    pub fn render_to_bytes<F, R>(&mut self, world: HittableList, progress: F) -> Vec<u8>
     where
-        F: FnMut(u32),
+        F: FnMut(u32) -> R,
+        R: Into<RenderControl>,
     {
         self.initialize();
-        let mut buffer = Vec::new();
+        let mut buffer = Vec::with_capacity((self.image_width * self.image_height * 3) as usize);
 
-        for j in 0..=self.image_height - 1 {
-            for i in 0..=self.image_width - 1 {
-                let mut pixel_color = Color::from(0.0);
+        let output = self.output_transform();
+        for pixel_color in self
+            .render_film(&world, self.image_tile(), 0..self.samples, None, progress)
+            .pixels()
+        {
+            buffer.extend_from_slice(&output.to_rgb_bytes(pixel_color));
+        }
+        buffer
+    }
 
-                for _ in 0..self.samples {
-                    let r = self.get_ray(i, j);
-                    pixel_color += self.ray_color(r, self.bounces, &world);
+    /// Renders the scene to a [`Film`] of linear colors, runs progress callback every tile
+    /// * `world` - The HittableList representing the scene
+    /// * `progress` - A callback function with u32 parameter, which can return [`RenderControl::Stop`] to stop the render early.
+    ///
+    /// The colors are left as they are, without any exposure, tone mapping or encoding, so values brighter than 1.0 are kept. Useful for saving HDR images, or encoding the image yourself with `output_transform`.
+    pub fn render_to_film<F, R>(&mut self, world: HittableList, progress: F) -> Film
+    where
+        F: FnMut(u32) -> R,
+        R: Into<RenderControl>,
+    {
+        self.initialize();
+        self.render_film(&world, self.image_tile(), 0..self.samples, None, progress)
+    }
+
+    /// Renders the scene for up to `budget`, and returns the best image it got in that time.
+    /// * `world` - The HittableList representing the scene
+    /// * `budget` - How long to render for.
+    ///
+    /// The image is rendered in passes, each adding a few samples to every pixel, until the time runs out or `samples` is reached. Set `samples` high to use the whole budget.
+    /// The pass that is running when the time runs out is stopped, the tiles it already finished are kept.
+    pub fn render_for(&mut self, world: HittableList, budget: Duration) -> Film {
+        self.initialize();
+        let deadline = Instant::now() + budget;
+        let mut film = Film::with_filter(self.image_width, self.image_height, self.filter)
+            .with_aovs(&self.aovs);
+        let (mut done, mut pass) = (0, 1);
+        while done < self.samples && Instant::now() < deadline {
+            // passes start small, so there is a full image quickly, and grow to keep the overhead down
+            let end = (done + pass).min(self.samples);
+            let pass_film =
+                self.render_film(&world, self.image_tile(), done..end, Some(&film), |_| {
+                    if Instant::now() < deadline {
+                        RenderControl::Continue
+                    } else {
+                        RenderControl::Stop
+                    }
+                });
+            film.merge(&pass_film);
+            done = end;
+            pass = (pass * 2).min(16);
+        }
+        film
+    }
+
+    /// Renders part of the scene, returning a [`Film`] that only covers `region`, clipped to the image.
+    /// * `world` - The HittableList representing the scene
+    /// * `region` - The pixels to render, see [`Tile`]
+    /// * `progress` - A callback function with u32 parameter, run every tile, which can return [`RenderControl::Stop`] to stop the render early.
+    ///
+    /// The pixels are exactly the same as the ones from a full render, even with wide filters, so regions can be merged into a bigger [`Film`] to build up the whole image.
+    pub fn render_region<F, R>(&mut self, world: &HittableList, region: Tile, progress: F) -> Film
+    where
+        F: FnMut(u32) -> R,
+        R: Into<RenderControl>,
+    {
+        self.initialize();
+        self.render_film(world, region, 0..self.samples, None, progress)
+    }
+
+    /// Returns the tiles the premade renderers split the image into, in the order they are rendered.
+    pub fn tiles(&self) -> Tiles {
+        self.image_tile().split(self.tile_size)
+    }
+
+    /// Returns the tiles that are traced to render `region`, in the order they are rendered.
+    /// This includes tiles just outside the region, since wide filters spread their samples into it.
+    pub fn region_tiles(&self, region: Tile) -> Vec<Tile> {
+        // the tiles line up with the ones of a full render, so the pixels add up in the same order, and come out exactly the same
+        let reach = self.filter.radius().ceil() as u32;
+        let region = region.intersect(self.image_tile());
+        if region.is_empty() {
+            return Vec::new();
+        }
+        let traced = region.expand(reach, self.image_tile());
+        self.tiles()
+            .map(|tile| tile.intersect(traced))
+            .filter(|tile| !tile.is_empty())
+            .collect()
+    }
+
+    /// Renders the samples in `samples` for every pixel of `region` on `threads` threads, gathering them on a film with the camera's filter.
+    /// Tiles are handed out to whichever thread is free next, and the progress callback is run on the calling thread with the number of tiles finished before the current one.
+    /// Each tile is rendered onto its own small film, since wide filters spread samples into the pixels around it. The tile films are merged in order, so the result doesn't depend on which thread finishes first.
+    /// If the callback returns [`RenderControl::Stop`], no new tiles are started, and the tiles that are already done are kept.
+    /// `previous` is a film with the samples already traced in earlier passes, if there are any, so adaptive sampling can skip pixels that are already clean.
+    pub(crate) fn render_film<F, R>(
+        &self,
+        world: &HittableList,
+        region: Tile,
+        samples: Range<u32>,
+        previous: Option<&Film>,
+        mut progress: F,
+    ) -> Film
+    where
+        F: FnMut(u32) -> R,
+        R: Into<RenderControl>,
+    {
+        let region = region.intersect(self.image_tile());
+        let mut film = Film::window(
+            (region.x, region.y),
+            (region.width, region.height),
+            self.filter,
+        )
+        .with_aovs(&self.aovs);
+        if region.is_empty() {
+            return film;
+        }
+        let tiles = self.region_tiles(region);
+        let next_tile = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            for _ in 0..self.threads.max(1) {
+                let sender = sender.clone();
+                let (tiles, next_tile, stop, samples) =
+                    (&tiles, &next_tile, &stop, samples.clone());
+                scope.spawn(move || loop {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                    let Some(&tile) = tiles.get(index) else {
+                        break;
+                    };
+                    let film = self.render_tile(tile, region, samples.clone(), previous, world);
+                    if sender.send((index, film)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            let mut finished_tiles = BTreeMap::new();
+            let mut next_merge = 0;
+            for (finished, (index, tile)) in receiver.iter().enumerate() {
+                finished_tiles.insert(index, tile);
+                while let Some(tile) = finished_tiles.remove(&next_merge) {
+                    film.merge(&tile);
+                    next_merge += 1;
+                }
+                if !stop.load(Ordering::Relaxed)
+                    && progress(finished as u32).into() == RenderControl::Stop
+                {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+            // a stopped render leaves gaps, so the tiles after them are never merged in order
+            for tile in finished_tiles.values() {
+                film.merge(tile);
+            }
+        });
+        film
+    }
+
+    /// Traces one ray for each sample in `samples` through every pixel of `tile`, onto a film covering the pixels of `region` the filter can reach.
+    /// With adaptive sampling, pixels stop early once they are clean enough, counting the samples from `previous` too.
+    fn render_tile(
+        &self,
+        tile: Tile,
+        region: Tile,
+        samples: Range<u32>,
+        previous: Option<&Film>,
+        world: &HittableList,
+    ) -> Film {
+        let reach = self.filter.radius().ceil() as u32;
+        let window = tile.expand(reach, region);
+        let mut film = Film::window(
+            (window.x, window.y),
+            (window.width, window.height),
+            self.filter,
+        )
+        .with_aovs(&self.aovs);
+        let mut values = Vec::with_capacity(self.aovs.len());
+
+        let mut sampler = self.sampler.build(self.seed, self.samples);
+        for j in tile.y..tile.y + tile.height {
+            for i in tile.x..tile.x + tile.width {
+                let mut stats = previous.map_or(PixelStats::default(), |film| film.stats(i, j));
+                for s in samples.clone() {
+                    if self.is_converged(stats) {
+                        break;
+                    }
+                    sampler.start_sample((i, j), s);
+                    let offset = sample_square(&mut *sampler);
+                    let (x, y) = (i as f64 + 0.5 + offset.x, j as f64 + 0.5 + offset.y);
+                    let r = self.get_ray_at(x, y, &mut *sampler);
+                    let color = if self.aovs.is_empty() {
+                        self.integrator.radiance(r, self, world, &mut *sampler)
+                    } else {
+                        let mut aov_sample = AovSample::default();
+                        let color = self.integrator.radiance_with_aovs(
+                            r,
+                            self,
+                            world,
+                            &mut *sampler,
+                            &mut aov_sample,
+                        );
+                        values.clear();
+                        values.extend(self.aovs.iter().map(|aov| aov.value(&aov_sample)));
+                        color
+                    };
+                    film.add_sample_with_aovs(x, y, color, &values);
+                    film.record_sample(i, j, color);
+                    stats.add(color);
                 }
-                buffer.extend_from_slice(&(pixel_color * self.sample_scale).to_rgb_bytes());
             }
-            progress(j);
         }
-        buffer
+        film
+    }
+
+    /// Returns true if adaptive sampling is on, and a pixel with these statistics is clean enough to stop.
+    fn is_converged(&self, stats: PixelStats) -> bool {
+        self.noise_threshold > 0.0
+            && stats.count() >= self.min_samples.max(2)
+            && stats.error() < self.noise_threshold
+    }
+
+    /// The tile covering the whole image.
+    fn image_tile(&self) -> Tile {
+        Tile::new(0, 0, self.image_width, self.image_height)
     }
     /// Initalizes camera settings based on current properties.
     /// This should be run any time the resolution, location, lookat, sample count, focus amount or focus distance is changed.
@@ -195,15 +503,24 @@ impl Camera {
     /// Creates an initial ray targeting pixel (i, j)
     /// The rays are slightly jittered, to acheive proper multi-sample averages.
     /// * `i`, `j` - The target pixel
-    pub fn get_ray(&self, i: u32, j: u32) -> Ray {
+    /// * `sampler` - The source of random numbers
+    pub fn get_ray(&self, i: u32, j: u32, sampler: &mut dyn Sampler) -> Ray {
         //creates rays from defocus disk pointing at a random point in pixel i, j
-        let offset = sample_square();
-
-        let pixel_sample = self.pixel00_loc
-            + ((i as f64 + offset.x) * self.pixel_delta_u)
-            + ((j as f64 + offset.y) * self.pixel_delta_v);
+        let offset = sample_square(sampler);
+        self.get_ray_at(
+            i as f64 + 0.5 + offset.x,
+            j as f64 + 0.5 + offset.y,
+            sampler,
+        )
+    }
+    /// Creates an initial ray targeting a point on the image, in pixels. The center of pixel (0, 0) is at (0.5, 0.5).
+    /// * `x`, `y` - The target point
+    /// * `sampler` - The source of random numbers, used to pick a point on the lens
+    pub fn get_ray_at(&self, x: f64, y: f64, sampler: &mut dyn Sampler) -> Ray {
+        let pixel_sample =
+            self.pixel00_loc + ((x - 0.5) * self.pixel_delta_u) + ((y - 0.5) * self.pixel_delta_v);
 
-        let ray_origin = defocus_disk_sample(&self);
+        let ray_origin = defocus_disk_sample(self, sampler);
         let ray_direction = pixel_sample - ray_origin;
 
         Ray::new(ray_origin, ray_direction)
@@ -212,39 +529,37 @@ impl Camera {
     pub fn get_distance(&self, target: Point3) -> f64 {
         (target - self.lookfrom).length()
     }
+    /// Returns the depth of a point, its distance from the camera along the view direction, negative behind the camera.
+    pub fn depth(&self, p: Point3) -> f64 {
+        dot(&(p - self.center), &-self.w)
+    }
     /// Return's the canera's sample scale, Should generally be used as opposed to acessing the value directly, as it should not be modified mid-trace.
     pub fn get_sample_scale(&self) -> f64 {
         self.sample_scale
     }
 
-    /// Traces a ray through the scene, and outputs a final color.
+    /// Traces a ray through the scene with the default path tracer, and outputs a final color.
+    /// This always path traces, whatever the camera's `integrator` is, use `integrator.radiance` to trace with that instead.
     /// * `r` - The `Ray` to be traced.
     /// * `bounces` - The maximum depth of the trace.
     /// * `world` - A HittableList of objects, representing the scene.
-    pub fn ray_color(&self, r: Ray, bounces: u32, world: &HittableList) -> Color {
-        //actually traces the
-        //ray
-        if bounces == 0 {
-            return Color::from(0.);
-        }
-
-        let mut rec: HitRecord = Default::default();
-
-        if world.hit(&r, 0.001..f64::INFINITY, &mut rec) {
-            let mut scattered = Ray::new(Vec3::from(0.), Vec3::from(0.));
-            let mut attenuation = Color::from(1.);
-
-            if rec.mat.scatter(&r, &rec, &mut attenuation, &mut scattered) {
-                //does bounce/scattter for materials of hit object
-                return attenuation * self.ray_color(scattered, bounces - 1, world);
-            }
-
-            return Color::new(0., 0., 0.); // Show up around the edge of metals
+    /// * `sampler` - The source of random numbers, passed on to the materials.
+    pub fn ray_color(
+        &self,
+        r: Ray,
+        bounces: u32,
+        world: &HittableList,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        PathIntegrator.trace(r, bounces, self, world, sampler)
+    }
+    /// Returns the steps used to turn the linear render into bytes, built from the `exposure`, `tone_map` and `transfer` settings.
+    pub fn output_transform(&self) -> OutputTransform {
+        OutputTransform {
+            exposure: self.exposure,
+            tone_map: self.tone_map,
+            transfer: self.transfer,
         }
-
-        // if the ray hits nothing, calculates a sky color
-
-        return self.sky.color(r);
     }
     /// Returns the height of the camera's image
     pub fn get_height(&self) -> u32 {
@@ -279,31 +594,64 @@ impl Default for Camera {
                 start: Color::new(0.5, 0.7, 1.0),
                 end: Color::new(1.0, 1.0, 1.0),
             }),
+            integrator: Box::new(PathIntegrator),
+            aovs: Vec::new(),
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            tile_size: 32,
+            exposure: 0.0,
+            tone_map: ToneMap::None,
+            transfer: TransferFunction::Srgb,
+            filter: Filter::default(),
+            seed: 0,
+            sampler: SamplerKind::default(),
+            noise_threshold: 0.0,
+            min_samples: 16,
         }
     }
 }
 
-/// Creates a vector with the X and Y origins set as a random point between 0 and 1
-fn sample_square() -> Vec3 {
-    let mut rng = thread_rng();
-    Vec3::new(rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0), 0.)
+/// Creates a vector with the X and Y origins set as a random point between -0.5 and 0.5, an offset from the center of a pixel
+fn sample_square(sampler: &mut dyn Sampler) -> Vec3 {
+    let (x, y) = sampler.next_2d();
+    Vec3::new(x - 0.5, y - 0.5, 0.)
 }
 /// Creates a point somehwere in the area of the camera's virtual "lens", used to simulate DOF
-fn defocus_disk_sample(cam: &Camera) -> Point3 {
-    let p = Vec3::random_in_unit_disk();
+fn defocus_disk_sample(cam: &Camera, sampler: &mut dyn Sampler) -> Point3 {
+    let p = Vec3::random_in_unit_disk(sampler);
     cam.center + (p.x * cam.defocus_disc_u) + (p.y * cam.defocus_disc_v)
 }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+/// What a render should do next, returned from the progress callback of the render functions.
+/// Callbacks that return nothing always continue, since `()` converts to `Continue`.
+pub enum RenderControl {
+    /// Keep rendering
+    #[default]
+    Continue,
+    /// Stop rendering as soon as possible, returning the tiles finished so far. Tiles that were never rendered are left black.
+    Stop,
+}
+
+impl From<()> for RenderControl {
+    fn from(_: ()) -> Self {
+        RenderControl::Continue
+    }
+}
 
 /// Any object that implements the `Sky` trait can be used as a sky for the camera.
 /// For a simple sky, you can use the [`Color`] struct which renders a solid color, or the [`GradientSky`] struct which renders a gradient between two colors.
 /// You can also implement your own sky by implementing the `Sky` trait for your struct.
-pub trait Sky: SkyClone {
+pub trait Sky: SkyClone + Send + Sync {
     /// Returns the color of the sky for a given ray
     fn color(&self, ray: Ray) -> Vec3;
+    /// Describes the sky, so it can be saved in a scene file. Returns `None` if it can't be described.
+    fn describe(&self) -> Option<SkyDescription> {
+        None
+    }
 }
 
 /// A trait to allow cloning of a `Sky` object, its useful.
 pub trait SkyClone {
+    /// Clones the sky as a boxed trait object.
     fn clone_box(&self) -> Box<dyn Sky>;
 }
 
@@ -346,6 +694,9 @@ impl Sky for Color {
     fn color(&self, _direction: Ray) -> Vec3 {
         *self
     }
+    fn describe(&self) -> Option<SkyDescription> {
+        Some(SkyDescription::Solid { color: *self })
+    }
 }
 
 impl Sky for GradientSky {
@@ -353,4 +704,10 @@ impl Sky for GradientSky {
         let t = 0.5 * (ray.direction.normalized().y + 1.0);
         self.start * (1.0 - t) + self.end * t
     }
+    fn describe(&self) -> Option<SkyDescription> {
+        Some(SkyDescription::Gradient {
+            start: self.start,
+            end: self.end,
+        })
+    }
 }
@@ -0,0 +1,223 @@
+//! This module contains [`Checkpoint`], a snapshot of a render in progress that can be saved to disk, and resumed later.
+//! Big renders can take hours, so a [`ProgressiveRenderer`](crate::progressive::ProgressiveRenderer) can save a checkpoint every so often, and if the render is stopped ( or the machine restarts ), it picks up from the last one instead of starting over.
+//!
+//! A checkpoint holds everything the renderer has gathered: the film with its AOV layers and per-pixel statistics, the number of samples traced, and the seed. The premade renderers restart their sampler from the seed for every sample, so that's all the random number state there is, and a resumed render traces exactly the samples the original one would have.
+//! It also holds a hash of the scene ( see `SceneDescription::film_hash` ), so a checkpoint can't be resumed with a scene or camera it wasn't rendered with. The sample count isn't part of it, so a finished render can be resumed with more samples to clean it up further.
+//!
+//! # Example
+//! ```no_run
+//! use rtwlib::{camera::Camera, checkpoint::Checkpoint, hittable::HittableList, progressive::ProgressiveRenderer};
+//!
+//! let (cam, world) = (Camera::new(), HittableList::new());
+//! let mut renderer = match Checkpoint::load("render.ckpt", &cam.aovs) {
+//!     Ok(checkpoint) => ProgressiveRenderer::resume(cam, world, checkpoint).expect("the scene changed"),
+//!     Err(_) => ProgressiveRenderer::new(cam, world),
+//! };
+//! while !renderer.is_done() {
+//!     renderer.render_pass(4);
+//!     renderer.checkpoint().save("render.ckpt").expect("failed to save");
+//! }
+//! ```
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{aov::Aov, film::Film};
+
+/// The first bytes of every checkpoint file.
+const MAGIC: &[u8; 8] = b"rtwckpt\n";
+/// The version of the file layout, bumped whenever it changes.
+const VERSION: u32 = 1;
+/// The longest string a checkpoint can hold, names are much shorter, so anything longer is a corrupt file.
+const MAX_STRING: u32 = 4096;
+
+#[derive(Clone, Debug)]
+/// A render in progress, see the module docs.
+pub struct Checkpoint {
+    film: Film,
+    samples: u32,
+    seed: u64,
+    scene_hash: Option<u64>,
+}
+
+#[derive(Debug)]
+/// An error from loading or resuming a [`Checkpoint`].
+pub enum CheckpointError {
+    /// The file couldn't be read or written.
+    Io(io::Error),
+    /// The file isn't a checkpoint, or is from a newer version.
+    Format(String),
+    /// The film has a layer for an AOV the render doesn't know about, like a mask that isn't in the camera's `aovs`.
+    UnknownAov(String),
+    /// The checkpoint was rendered with a different scene, camera or seed, or without one of the camera's AOVs.
+    SceneChanged,
+}
+
+impl Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(err) => write!(f, "{}", err),
+            CheckpointError::Format(what) => write!(f, "invalid checkpoint: {}", what),
+            CheckpointError::UnknownAov(name) => write!(f, "unknown aov '{}'", name),
+            CheckpointError::SceneChanged => {
+                write!(f, "the checkpoint was rendered with a different scene")
+            }
+        }
+    }
+}
+
+impl Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CheckpointError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        CheckpointError::Io(err)
+    }
+}
+
+impl Checkpoint {
+    /// Creates a new `Checkpoint` of `film`, after `samples` samples per pixel rendered with `seed`.
+    /// `scene_hash` is the `film_hash` of the scene's description, or `None` if it can't be described. Checkpoints without a hash can only be resumed with scenes that can't be described either.
+    pub fn new(film: Film, samples: u32, seed: u64, scene_hash: Option<u64>) -> Self {
+        Checkpoint {
+            film,
+            samples,
+            seed,
+            scene_hash,
+        }
+    }
+    /// Returns the film rendered so far.
+    pub fn film(&self) -> &Film {
+        &self.film
+    }
+    /// Consumes the checkpoint, returning its film.
+    pub fn into_film(self) -> Film {
+        self.film
+    }
+    /// Returns the number of samples traced through each pixel so far.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+    /// Returns the seed the samples were rendered with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Returns the hash of the scene the samples were rendered with, if there is one.
+    pub fn scene_hash(&self) -> Option<u64> {
+        self.scene_hash
+    }
+    /// Saves the checkpoint to a file.
+    /// It's written to a temporary file next to it first, and moved into place once it's done, so a crash while saving doesn't lose the last checkpoint.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+
+        let mut w = BufWriter::new(File::create(&temp)?);
+        self.write(&mut w)?;
+        w.into_inner()?.sync_all()?;
+        fs::rename(&temp, path)
+    }
+    /// Writes the checkpoint to a writer, in the same format as `save`.
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&self.samples.to_le_bytes())?;
+        w.write_all(&self.seed.to_le_bytes())?;
+        w.write_all(&[self.scene_hash.is_some() as u8])?;
+        w.write_all(&self.scene_hash.unwrap_or(0).to_le_bytes())?;
+        self.film.write_state(w)
+    }
+    /// Loads a checkpoint from a file.
+    /// AOV layers are matched up by name with `aovs`, which should be the camera's, so mask layers get their materials back. Built-in AOVs are found without it.
+    pub fn load<P: AsRef<Path>>(path: P, aovs: &[Aov]) -> Result<Self, CheckpointError> {
+        Checkpoint::read(&mut BufReader::new(File::open(path)?), aovs)
+    }
+    /// Reads a checkpoint from a reader, see `load`.
+    /// Files that are cut short or corrupt return an error.
+    /// # Example
+    /// ```
+    /// use rtwlib::{checkpoint::Checkpoint, film::Film};
+    ///
+    /// let mut file = Vec::new();
+    /// Checkpoint::new(Film::new(16, 9), 4, 0, None).write(&mut file).unwrap();
+    /// assert!(Checkpoint::read(&mut file.as_slice(), &[]).is_ok());
+    ///
+    /// // a file that was cut short
+    /// assert!(Checkpoint::read(&mut &file[..file.len() / 2], &[]).is_err());
+    /// // a corrupt film size, claiming to be 4294967295 by 4294967295 pixels, the size comes after a 33 byte header
+    /// let mut corrupt = file.clone();
+    /// corrupt[33..41].fill(0xff);
+    /// assert!(Checkpoint::read(&mut corrupt.as_slice(), &[]).is_err());
+    /// // not a checkpoint at all
+    /// assert!(Checkpoint::read(&mut &b"garbage"[..], &[]).is_err());
+    /// ```
+    pub fn read<R: Read>(r: &mut R, aovs: &[Aov]) -> Result<Self, CheckpointError> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(CheckpointError::Format("not a checkpoint".to_string()));
+        }
+        let version = read_u32(r)?;
+        if version != VERSION {
+            return Err(CheckpointError::Format(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let samples = read_u32(r)?;
+        let seed = read_u64(r)?;
+        let mut has_hash = [0];
+        r.read_exact(&mut has_hash)?;
+        let hash = read_u64(r)?;
+        Ok(Checkpoint {
+            film: Film::read_state(r, aovs)?,
+            samples,
+            seed,
+            scene_hash: (has_hash[0] != 0).then_some(hash),
+        })
+    }
+}
+
+pub(crate) fn read_u32(r: &mut dyn Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_u64(r: &mut dyn Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_f64(r: &mut dyn Read) -> io::Result<f64> {
+    Ok(f64::from_bits(read_u64(r)?))
+}
+
+/// Reads a string written by `write_string`, no longer than `MAX_STRING` bytes.
+pub(crate) fn read_string(r: &mut dyn Read) -> Result<String, CheckpointError> {
+    let len = read_u32(r)?;
+    if len > MAX_STRING {
+        return Err(CheckpointError::Format("name too long".to_string()));
+    }
+    let mut bytes = vec![0; len as usize];
+    r.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| CheckpointError::Format("invalid name".to_string()))
+}
+
+/// Writes a string as its length, then its UTF-8 bytes.
+pub(crate) fn write_string(w: &mut dyn Write, s: &str) -> io::Result<()> {
+    w.write_all(&(s.len() as u32).to_le_bytes())?;
+    w.write_all(s.as_bytes())
+}
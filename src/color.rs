@@ -1,5 +1,7 @@
 //! This module contans all functions and structs related to colors and color manipulation.
 //! This includes the `Color` struct, an alias for `Vec3`, and functions to convert colors to different formats, as well as color manipulation functions such as gamma correction.
+//!
+//! Colors are rendered in linear light, and encoded with a [`TransferFunction`] ( sRGB by default ) when they are turned into bytes.
 use crate::vec3::Vec3;
 
 ///Converts a linear color value to a gamma corrected value.
@@ -32,34 +34,122 @@ pub fn gamma_color_to_linear(color: Vec3) -> Vec3 {
     }
 }
 
-impl Color {
-    ///Converts a color to a byte array containing the RGB values of the color.
-    pub fn to_rgb_bytes(mut self) -> [u8; 3] {
-        self = gamma_color_to_linear(self);
+/// Converts a linear color value to sRGB, using the piecewise curve from the sRGB spec.
+/// This is what image viewers expect 8 bit images to be encoded with.
+pub fn linear_to_srgb(linear: f64) -> f64 {
+    if linear <= 0.003_130_8 {
+        12.92 * linear.max(0.)
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Converts an sRGB encoded color value to a linear value.
+/// This is the inverse of `linear_to_srgb`.
+pub fn srgb_to_linear(srgb: f64) -> f64 {
+    if srgb <= 0.040_45 {
+        srgb.max(0.) / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+/// The curve used to encode linear colors before they are quantized to bytes.
+/// Renders are computed in linear light, but 8 bit images store values on a curve, so the dark tones get more of the 256 levels.
+pub enum TransferFunction {
+    /// The piecewise sRGB curve, the right choice for almost every image format.
+    #[default]
+    Srgb,
+    /// A plain power curve, `value.powf(1.0 / gamma)`. A gamma of 2.0 matches `linear_to_gamma`.
+    Gamma(f64),
+    /// No encoding, the linear values are quantized as they are.
+    Linear,
+}
+
+impl TransferFunction {
+    /// Encodes a linear value with this curve.
+    pub fn encode(self, linear: f64) -> f64 {
+        match self {
+            TransferFunction::Srgb => linear_to_srgb(linear),
+            TransferFunction::Gamma(gamma) => linear.max(0.).powf(1. / gamma),
+            TransferFunction::Linear => linear,
+        }
+    }
+    /// Decodes a value encoded with this curve back to linear.
+    pub fn decode(self, encoded: f64) -> f64 {
+        match self {
+            TransferFunction::Srgb => srgb_to_linear(encoded),
+            TransferFunction::Gamma(gamma) => encoded.max(0.).powf(gamma),
+            TransferFunction::Linear => encoded,
+        }
+    }
+    /// Encodes every channel of a linear color with this curve.
+    pub fn encode_color(self, color: Color) -> Color {
+        Color::new(
+            self.encode(color.x),
+            self.encode(color.y),
+            self.encode(color.z),
+        )
+    }
+    /// Decodes every channel of an encoded color back to linear.
+    pub fn decode_color(self, color: Color) -> Color {
+        Color::new(
+            self.decode(color.x),
+            self.decode(color.y),
+            self.decode(color.z),
+        )
+    }
+}
 
-        let intensity = (0.0, 0.999);
-        //convert colors from 0-1 f64 range to 8 bit integer (0-255)
-        let rbyte = (self.x.clamp(intensity.0, intensity.1) * 255.0) as u8;
-        let gbyte = (self.y.clamp(intensity.0, intensity.1) * 255.) as u8;
-        let bbyte = (self.z.clamp(intensity.0, intensity.1) * 255.) as u8;
+/// Quantizes a 0-1 value to a byte, rounding to the nearest level.
+fn to_byte(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
 
-        [rbyte, gbyte, bbyte]
+impl Color {
+    ///Converts a linear color to a byte array containing the sRGB encoded RGB values of the color.
+    pub fn to_rgb_bytes(self) -> [u8; 3] {
+        self.to_rgb_bytes_with(TransferFunction::Srgb)
+    }
+    ///Converts a linear color to a byte array, encoding it with the given transfer function first.
+    pub fn to_rgb_bytes_with(self, transfer: TransferFunction) -> [u8; 3] {
+        let encoded = transfer.encode_color(self);
+        [to_byte(encoded.x), to_byte(encoded.y), to_byte(encoded.z)]
+    }
+    ///Converts a color to a byte array without any encoding, for callers that want the raw linear values.
+    pub fn to_linear_rgb_bytes(self) -> [u8; 3] {
+        self.to_rgb_bytes_with(TransferFunction::Linear)
+    }
+    ///Converts sRGB encoded bytes, like the ones from `to_rgb_bytes`, back to a linear color.
+    pub fn from_rgb_bytes(rgb: [u8; 3]) -> Color {
+        TransferFunction::Srgb.decode_color(Color::new(
+            rgb[0] as f64 / 255.0,
+            rgb[1] as f64 / 255.0,
+            rgb[2] as f64 / 255.0,
+        ))
+    }
+    ///Returns the brightness of a linear color, as the eye sees it, using the Rec. 709 weights.
+    pub fn luminance(self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
     }
-    ///Converts a color to a hexadecimal string, starting with a `#`.
+    ///Converts a linear color to an sRGB hexadecimal string, starting with a `#`.
     pub fn to_hex(&self) -> String {
         let bytes = self.to_rgb_bytes();
         format!("#{:02x}{:02x}{:02x}", bytes[0], bytes[1], bytes[2])
     }
-    ///Converts a hexadecimal string to a color.
+    ///Converts an sRGB hexadecimal string, like the ones from color pickers, to a linear color.
     pub fn from_hex(hex: &str) -> Result<Vec3, std::num::ParseIntError> {
         let hex = hex.trim_start_matches('#');
         let r = u8::from_str_radix(&hex[0..2], 16)?;
         let g = u8::from_str_radix(&hex[2..4], 16)?;
         let b = u8::from_str_radix(&hex[4..6], 16)?;
-        let r = r as f64 / 255.0;
-        let g = g as f64 / 255.0;
-        let b = b as f64 / 255.0;
-        Ok(Vec3::new(r, g, b))
+        Ok(Color::from_rgb_bytes([r, g, b]))
     }
 }
 
@@ -0,0 +1,251 @@
+//! This module contains the [`Denoiser`], which cleans up the noise in renders with few samples per pixel.
+//! It's an edge-avoiding à-trous wavelet filter ( from "Edge-Avoiding À-Trous Wavelet Transform for fast Global Illumination Filtering" by Dammertz et al. ), which blurs each pixel with its neighbors over a few passes, each one reaching twice as far as the last.
+//! The blur is guided by the albedo, normal and depth AOVs of the render, so it stops at the edges of objects, and at creases in them, instead of smearing them together. The lighting is filtered without the albedo, so textures stay sharp too.
+//!
+//! The guides are taken from the film's AOV layers, so the camera needs to render them, see the [`aov`](crate::aov) module. Guides the film doesn't have are skipped, which still works, but blurs more edges.
+//! Rendered films also know how noisy each pixel is, so clean pixels are blurred less than noisy ones.
+//!
+//! # Example
+//! ```no_run
+//! use rtwlib::{aov::Aov, camera::Camera, denoise::Denoiser, hittable::HittableList, image};
+//!
+//! let mut cam = Camera::new();
+//! cam.samples = 16;
+//! cam.aovs = vec![Aov::Albedo, Aov::Normal, Aov::Depth];
+//! let film = cam.render_to_film(HittableList::new(), |_| {});
+//!
+//! let clean = Denoiser::new().denoise(&film);
+//! image::save_film("render.png", &clean, &cam.output_transform()).expect("failed to save");
+//! ```
+use crate::{aov::Aov, color::Color, film::Film, vec3::*};
+
+/// The weights of the 5 taps of the filter along each axis, a B3 spline.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+/// Albedos darker than this aren't divided out, so the sky and black surfaces don't blow up.
+const MIN_ALBEDO: f64 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An edge-avoiding à-trous denoiser, see the module docs.
+/// Each `sigma` sets how different two pixels can be before they stop being blurred together, higher values blur more.
+pub struct Denoiser {
+    /// The number of passes, each one reaches twice as far as the last, so 5 passes blur pixels up to 62 pixels away
+    pub iterations: u32,
+    /// How different the lighting of two pixels can be, in multiples of the pixel's noise
+    pub color_sigma: f64,
+    /// How different the normals of two pixels can be, as the distance between them
+    pub normal_sigma: f64,
+    /// How different the depths of two pixels can be, in multiples of how fast the depth is changing around the pixel
+    pub depth_sigma: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser {
+            iterations: 5,
+            color_sigma: 2.0,
+            normal_sigma: 0.3,
+            depth_sigma: 1.0,
+        }
+    }
+}
+
+/// The feature buffers guiding the filter, in rows from top to bottom.
+struct Guides {
+    width: usize,
+    height: usize,
+    albedo: Option<Vec<Color>>,
+    normal: Option<Vec<Vec3>>,
+    depth: Option<Vec<f64>>,
+    /// How fast the depth changes along x and y at each pixel
+    depth_gradient: Vec<(f64, f64)>,
+    /// The noise of each pixel, or infinity if it isn't known
+    noise: Vec<f64>,
+}
+
+impl Denoiser {
+    /// Creates a new `Denoiser` with the default settings.
+    pub fn new() -> Self {
+        Denoiser::default()
+    }
+    /// Returns a denoised copy of `film`, guided by its albedo, normal and depth layers.
+    /// The AOV layers themselves are copied over as they are.
+    pub fn denoise(&self, film: &Film) -> Film {
+        let guides = Guides::new(film);
+        let albedo = |index: usize| {
+            guides.albedo.as_ref().map_or(Color::from(1.0), |albedo| {
+                // channels too dark to divide out are left as they are
+                let a = albedo[index];
+                Color::new(
+                    if a.x < MIN_ALBEDO { 1.0 } else { a.x },
+                    if a.y < MIN_ALBEDO { 1.0 } else { a.y },
+                    if a.z < MIN_ALBEDO { 1.0 } else { a.z },
+                )
+            })
+        };
+
+        // the lighting is filtered on its own, and the albedo is put back at the end
+        let mut lighting: Vec<Color> = film
+            .pixels()
+            .into_iter()
+            .enumerate()
+            .map(|(index, color)| color / albedo(index))
+            .collect();
+        for iteration in 0..self.iterations {
+            lighting = self.pass(&lighting, &guides, &albedo, 1 << iteration.min(16));
+        }
+
+        let mut denoised = film.clone();
+        denoised.set_pixels(
+            lighting
+                .into_iter()
+                .enumerate()
+                .map(|(index, color)| color * albedo(index))
+                .collect(),
+        );
+        denoised
+    }
+
+    /// Runs one pass of the filter, with the taps `step` pixels apart.
+    fn pass(
+        &self,
+        lighting: &[Color],
+        guides: &Guides,
+        albedo: &dyn Fn(usize) -> Color,
+        step: usize,
+    ) -> Vec<Color> {
+        let (width, height) = (guides.width, guides.height);
+        // the lighting gets smoother with each pass, so the color sigma shrinks to keep the edges
+        let color_sigma = self.color_sigma / step as f64;
+        // compares lighting in the same 0-1 brightness range the noise estimate uses
+        let brightness = |color: Color| {
+            let luminance = color.luminance().max(0.0);
+            luminance / (1.0 + luminance)
+        };
+
+        let mut filtered = Vec::with_capacity(lighting.len());
+        for y in 0..height {
+            for x in 0..width {
+                let center = y * width + x;
+                let center_brightness = brightness(lighting[center] * albedo(center));
+                let (mut sum, mut total) = (Color::from(0.0), 0.0);
+
+                for (j, ky) in KERNEL.iter().enumerate() {
+                    let Some(qy) = offset(y, j, step, height) else {
+                        continue;
+                    };
+                    for (i, kx) in KERNEL.iter().enumerate() {
+                        let Some(qx) = offset(x, i, step, width) else {
+                            continue;
+                        };
+                        let tap = qy * width + qx;
+                        let mut weight = kx * ky;
+
+                        // the tap's lighting on this pixel's surface, so textures don't count as edges
+                        let noise = guides.noise[center];
+                        if noise.is_finite() {
+                            let difference = (brightness(lighting[tap] * albedo(center))
+                                - center_brightness)
+                                .abs();
+                            weight *= (-difference / (color_sigma * noise + 1e-4)).exp();
+                        }
+                        if let Some(normal) = &guides.normal {
+                            let difference = (normal[tap] - normal[center]).length_squared();
+                            weight *= (-difference / (self.normal_sigma * self.normal_sigma)).exp();
+                        }
+                        if let Some(depth) = &guides.depth {
+                            // how much the depth should change over this distance on a smooth surface
+                            let (dx, dy) = guides.depth_gradient[center];
+                            let expected = (dx * (qx as f64 - x as f64)).abs()
+                                + (dy * (qy as f64 - y as f64)).abs();
+                            let difference = (depth[tap] - depth[center]).abs();
+                            weight *= (-difference / (self.depth_sigma * expected + 1e-4)).exp();
+                        }
+
+                        sum += weight * lighting[tap];
+                        total += weight;
+                    }
+                }
+                filtered.push(sum / total);
+            }
+        }
+        filtered
+    }
+}
+
+/// Returns the coordinate of tap `i` around `p`, or `None` if it's outside the image.
+fn offset(p: usize, i: usize, step: usize, len: usize) -> Option<usize> {
+    let q = p as i64 + (i as i64 - 2) * step as i64;
+    (0..len as i64).contains(&q).then_some(q as usize)
+}
+
+impl Guides {
+    fn new(film: &Film) -> Self {
+        let (width, height) = (film.width() as usize, film.height() as usize);
+        let layer = |aov: Aov| film.aov(&aov).map(|layer| layer.into_pixels());
+        let depth: Option<Vec<f64>> =
+            layer(Aov::Depth).map(|depth| depth.into_iter().map(|d| d.x).collect());
+
+        // the depth gradient is the smaller of the differences on either side, so it isn't thrown off by edges
+        let depth_gradient = match &depth {
+            Some(depth) => (0..width * height)
+                .map(|index| {
+                    let (x, y) = (index % width, index / width);
+                    let slope = |a: Option<usize>, b: Option<usize>| {
+                        [a, b]
+                            .into_iter()
+                            .flatten()
+                            .map(|q| (depth[q] - depth[index]).abs())
+                            .fold(f64::INFINITY, f64::min)
+                    };
+                    let dx = slope(
+                        offset(x, 1, 1, width).map(|x| y * width + x),
+                        offset(x, 3, 1, width).map(|x| y * width + x),
+                    );
+                    let dy = slope(
+                        offset(y, 1, 1, height).map(|y| y * width + x),
+                        offset(y, 3, 1, height).map(|y| y * width + x),
+                    );
+                    (
+                        if dx.is_finite() { dx } else { 0.0 },
+                        if dy.is_finite() { dy } else { 0.0 },
+                    )
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let noise: Vec<f64> = (0..height as u32)
+            .flat_map(|y| (0..width as u32).map(move |x| (x, y)))
+            .map(|(x, y)| film.noise(x, y))
+            .collect();
+        // a single pixel's noise is itself noisy with few samples, so it's averaged with its neighbors'
+        let noise = (0..width * height)
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                let (mut sum, mut count) = (0.0, 0.0);
+                for j in 1..4 {
+                    for i in 1..4 {
+                        if let (Some(qx), Some(qy)) =
+                            (offset(x, i, 1, width), offset(y, j, 1, height))
+                        {
+                            sum += noise[qy * width + qx].powi(2);
+                            count += 1.0;
+                        }
+                    }
+                }
+                (sum / count).sqrt()
+            })
+            .collect();
+
+        Guides {
+            width,
+            height,
+            albedo: layer(Aov::Albedo),
+            normal: layer(Aov::Normal),
+            depth,
+            depth_gradient,
+            noise,
+        }
+    }
+}
@@ -4,17 +4,17 @@ use rtwlib::hittable::*;
 use rtwlib::material::*;
 use rtwlib::sphere::*;
 use rtwlib::vec3::*;
-use std::rc::Rc;
+use std::sync::Arc;
 use std::{fs::File, io::Write};
 fn main() -> std::io::Result<()> {
     //Create a scene
     let mut world = HittableList::new();
 
     //Create materials, for the scene objects
-    let mat_ground = Rc::new(Lambertian::new(Color::new(0.3, 0.86, 0.1)));
-    let mat_center = Rc::new(Normal::new());
-    let mat_left = Rc::new(Dielectric::new(1.5));
-    let mat_right = Rc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.2));
+    let mat_ground = Arc::new(Lambertian::new(Color::new(0.3, 0.86, 0.1)));
+    let mat_center = Arc::new(Normal::new());
+    let mat_left = Arc::new(Dielectric::new(1.5));
+    let mat_right = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.2));
     /// Add spheres to the world
     world.add(Sphere::new(Point3::new(0., -100.5, -1.), 100.0, mat_ground)); // A large sphere to act as the ground
     world.add(Sphere::new(Point3::new(0., 0., -1.), 0.5, mat_center));
@@ -0,0 +1,569 @@
+//! This module contains the `Film` struct, a float image holding the linear radiance of a render.
+//! Unlike the byte buffers from `render_to_bytes`, a film keeps values brighter than 1.0, so it can be saved to HDR formats like OpenEXR, or tone mapped later on.
+//!
+//! Films are also where samples are gathered while rendering. Each sample is added with [`Film::add_sample`], and spread over the pixels around it by a reconstruction [`Filter`], which decides how sharp or smooth the final image looks.
+//!
+//! Films can also hold extra layers, one per [`Aov`], which are gathered from the same samples with the same filter. See the [`aov`](crate::aov) module.
+//!
+//! Films also keep track of how many samples were traced through each pixel, and how much they vary, which is what adaptive sampling uses to decide when a pixel is clean enough. See [`Film::noise`] and [`Film::sample_heatmap`].
+//!
+//! # Example
+//! ```no_run
+//! use rtwlib::{camera::Camera, film::Filter, hittable::HittableList, image};
+//!
+//! let mut cam = Camera::new();
+//! cam.filter = Filter::Mitchell { radius: 2.0 };
+//! let film = cam.render_to_film(HittableList::new(), |_| {});
+//! println!("the center pixel is {:?}", film.pixel(film.width() / 2, film.height() / 2));
+//! image::save_film("render.exr", &film, &cam.output_transform()).expect("failed to save");
+//! ```
+use std::{
+    f64::consts::PI,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    aov::Aov,
+    checkpoint::{read_f64, read_string, read_u32, write_string, CheckpointError},
+    color::{srgb_to_linear, Color},
+    tonemap::OutputTransform,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+/// A pixel reconstruction filter, which decides how much a sample counts towards each pixel around it.
+/// The `radius` is in pixels, filters wider than 0.5 blend samples into the neighboring pixels too.
+pub enum Filter {
+    /// Every sample inside the radius counts the same. With a radius of 0.5 this is a plain average of the samples in each pixel.
+    Box {
+        /// The radius in pixels, 0.5 is a good default
+        radius: f64,
+    },
+    /// Samples count less the further they are from the pixel, falling off in a straight line.
+    Tent {
+        /// The radius in pixels, 1.0 is a good default
+        radius: f64,
+    },
+    /// A smooth bell curve, softer than the others but very good at hiding aliasing.
+    Gaussian {
+        /// The radius in pixels, 1.5 is a good default
+        radius: f64,
+    },
+    /// The Mitchell-Netravali filter ( with B = C = 1/3 ), a good balance between sharpness and ringing.
+    Mitchell {
+        /// The radius in pixels, 2.0 is a good default
+        radius: f64,
+    },
+    /// A windowed sinc, the sharpest of the filters, but it can ring around hard edges.
+    Lanczos {
+        /// The radius in pixels, 3.0 is a good default
+        radius: f64,
+    },
+}
+
+impl Filter {
+    /// Returns the number the kind of filter is stored as, in checkpoints and scene hashes. These can't change, or old checkpoints are misread.
+    pub(crate) fn tag(self) -> u8 {
+        match self {
+            Filter::Box { .. } => 0,
+            Filter::Tent { .. } => 1,
+            Filter::Gaussian { .. } => 2,
+            Filter::Mitchell { .. } => 3,
+            Filter::Lanczos { .. } => 4,
+        }
+    }
+    /// Creates the filter stored as `tag` by [`Filter::tag`], with `radius`.
+    pub(crate) fn from_tag(tag: u8, radius: f64) -> Option<Filter> {
+        match tag {
+            0 => Some(Filter::Box { radius }),
+            1 => Some(Filter::Tent { radius }),
+            2 => Some(Filter::Gaussian { radius }),
+            3 => Some(Filter::Mitchell { radius }),
+            4 => Some(Filter::Lanczos { radius }),
+            _ => None,
+        }
+    }
+    /// Returns how far the filter reaches from a sample, in pixels.
+    pub fn radius(self) -> f64 {
+        match self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius }
+            | Filter::Mitchell { radius }
+            | Filter::Lanczos { radius } => radius,
+        }
+    }
+    /// Returns the weight of a sample that is (`dx`, `dy`) pixels away from a pixel center.
+    /// Mitchell and Lanczos can return negative weights, which is what makes them sharper.
+    pub fn evaluate(self, dx: f64, dy: f64) -> f64 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(self, x: f64) -> f64 {
+        let radius = self.radius();
+        let x = x.abs();
+        if x > radius {
+            return 0.0;
+        }
+        match self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { .. } => radius - x,
+            Filter::Gaussian { .. } => {
+                // the curve is shifted down, so it reaches 0 right at the radius
+                let sigma = radius / 3.0;
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            }
+            Filter::Mitchell { .. } => mitchell(2.0 * x / radius),
+            Filter::Lanczos { .. } => sinc(x) * sinc(x / radius),
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+/// The Mitchell-Netravali cubic, for `x` between 0 and 2.
+fn mitchell(x: f64) -> f64 {
+    const B: f64 = 1.0 / 3.0;
+    const C: f64 = 1.0 / 3.0;
+    let (x2, x3) = (x * x, x * x * x);
+    if x < 1.0 {
+        ((12.0 - 9.0 * B - 6.0 * C) * x3 + (-18.0 + 12.0 * B + 6.0 * C) * x2 + (6.0 - 2.0 * B))
+            / 6.0
+    } else {
+        ((-B - 6.0 * C) * x3
+            + (6.0 * B + 30.0 * C) * x2
+            + (-12.0 * B - 48.0 * C) * x
+            + (8.0 * B + 24.0 * C))
+            / 6.0
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x < 1e-5 {
+        return 1.0;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+#[derive(Clone, Debug)]
+/// A float image, storing one linear [`Color`] per pixel in rows from top to bottom.
+/// Each pixel is kept as a weighted sum of samples, and a total weight, which are divided when the pixel is read.
+pub struct Film {
+    width: u32,
+    height: u32,
+    /// The window of the image this film covers, films used by the render threads only hold a few lines
+    origin: (u32, u32),
+    filter: Filter,
+    sums: Vec<Color>,
+    weights: Vec<f64>,
+    /// The statistics of the samples traced through each pixel, before filtering
+    stats: Vec<PixelStats>,
+    /// The extra images, which share the weights of the main one
+    aovs: Vec<Aov>,
+    aov_sums: Vec<Vec<Color>>,
+}
+
+impl Film {
+    /// Creates a new black `Film`, which gathers samples with a box filter.
+    pub fn new(width: u32, height: u32) -> Self {
+        Film::with_filter(width, height, Filter::default())
+    }
+    /// Creates a new black `Film`, which gathers samples with `filter`.
+    pub fn with_filter(width: u32, height: u32, filter: Filter) -> Self {
+        Film::window((0, 0), (width, height), filter)
+    }
+    /// Creates a film covering part of a bigger image, starting at `origin`.
+    pub(crate) fn window(origin: (u32, u32), (width, height): (u32, u32), filter: Filter) -> Self {
+        let len = width as usize * height as usize;
+        Film {
+            width,
+            height,
+            origin,
+            filter,
+            sums: vec![Color::from(0.0); len],
+            weights: vec![0.0; len],
+            stats: vec![PixelStats::default(); len],
+            aovs: Vec::new(),
+            aov_sums: Vec::new(),
+        }
+    }
+    /// Adds an empty layer for each AOV, see the [`aov`](crate::aov) module. AOVs the film already has are skipped.
+    pub fn with_aovs(mut self, aovs: &[Aov]) -> Self {
+        for aov in aovs {
+            self.add_layer(aov);
+        }
+        self
+    }
+    /// Creates a `Film` from a list of linear colors, in rows from top to bottom.
+    /// Panics if there aren't exactly `width * height` colors.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<Color>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width as usize * height as usize,
+            "film size doesn't match"
+        );
+        let mut film = Film::new(width, height);
+        film.weights.fill(1.0);
+        film.sums = pixels;
+        film
+    }
+    /// Returns the width of the film in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    /// Returns the height of the film in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// Returns the filter used to gather samples.
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+    /// Returns the color of pixel (x, y), where (0, 0) is the top left.
+    /// Pixels without any samples are black.
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let index = self.index(x, y);
+        if self.weights[index] == 0.0 {
+            return Color::from(0.0);
+        }
+        self.sums[index] / self.weights[index]
+    }
+    /// Sets the color of pixel (x, y), replacing any samples it had.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        let index = self.index(x, y);
+        self.sums[index] = color;
+        self.weights[index] = 1.0;
+    }
+    /// Replaces the color of every pixel, in rows from top to bottom, keeping the AOV layers and sample statistics as they are.
+    /// Panics if there aren't exactly `width * height` colors.
+    pub fn set_pixels(&mut self, pixels: Vec<Color>) {
+        assert_eq!(pixels.len(), self.sums.len(), "film size doesn't match");
+        for (index, color) in pixels.into_iter().enumerate() {
+            // the layers share the weights, so they're kept and the new color is scaled up to match
+            if self.weights[index] == 0.0 {
+                self.weights[index] = 1.0;
+            }
+            self.sums[index] = self.weights[index] * color;
+        }
+    }
+    /// Adds a sample at (`x`, `y`), a point on the image in pixels, where the center of pixel (0, 0) is at (0.5, 0.5).
+    /// The sample is added to every pixel within the filter's radius, weighted by the filter. The AOV layers get black.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color) {
+        self.add_sample_with_aovs(x, y, color, &[]);
+    }
+    /// Adds a sample like `add_sample`, with a value for each AOV layer, in the order of [`Film::aovs`]. Layers without a value get black.
+    pub fn add_sample_with_aovs(&mut self, x: f64, y: f64, color: Color, aovs: &[Color]) {
+        let radius = self.filter.radius();
+        // the pixels whose centers are within the radius, clipped to this film, along one axis
+        let span = |p: f64, origin: u32, len: u32| {
+            let first = ((p - 0.5 - radius).ceil() as i64).max(origin as i64);
+            let last = ((p - 0.5 + radius).floor() as i64).min(origin as i64 + len as i64 - 1);
+            first..=last
+        };
+
+        for py in span(y, self.origin.1, self.height) {
+            // the filters are separable, so the weight of a pixel is just its x weight times its y weight
+            let y_weight = self.filter.evaluate_1d(py as f64 + 0.5 - y);
+            for px in span(x, self.origin.0, self.width) {
+                let x_weight = self.filter.evaluate_1d(px as f64 + 0.5 - x);
+                let weight = x_weight * y_weight;
+                if weight != 0.0 {
+                    let index = self.index(
+                        (px - self.origin.0 as i64) as u32,
+                        (py - self.origin.1 as i64) as u32,
+                    );
+                    self.sums[index] += weight * color;
+                    self.weights[index] += weight;
+                    for (sums, value) in self.aov_sums.iter_mut().zip(aovs) {
+                        sums[index] += weight * *value;
+                    }
+                }
+            }
+        }
+    }
+    /// Returns the pixel of the full image at the top left of this film. This is (0, 0), unless the film only covers part of an image, like the films from `Camera::render_region`.
+    pub fn origin(&self) -> (u32, u32) {
+        self.origin
+    }
+    /// Records a sample traced through pixel (`x`, `y`) of the full image in the pixel's statistics, samples outside this film are ignored.
+    /// This is separate from `add_sample`, since the statistics are about the samples traced through each pixel, not where the filter spreads them.
+    pub(crate) fn record_sample(&mut self, x: u32, y: u32, color: Color) {
+        if let Some(index) = self.local_index(x, y) {
+            self.stats[index].add(color);
+        }
+    }
+    /// Returns the statistics of pixel (`x`, `y`) of the full image, or empty statistics if it's outside this film.
+    pub(crate) fn stats(&self, x: u32, y: u32) -> PixelStats {
+        self.local_index(x, y)
+            .map_or(PixelStats::default(), |index| self.stats[index])
+    }
+    /// Returns the number of samples traced through pixel (x, y). This is the same for every pixel, unless adaptive sampling stopped some of them early.
+    pub fn sample_count(&self, x: u32, y: u32) -> u32 {
+        self.stats[self.index(x, y)].count
+    }
+    /// Returns an estimate of how noisy pixel (x, y) is, the standard error of its brightness. Lower is cleaner.
+    /// The brightness is squashed into the 0-1 range first ( with `l / (1 + l)` ), so a few very bright samples don't make a pixel look much noisier than it is. Pixels with less than 2 samples are infinitely noisy.
+    pub fn noise(&self, x: u32, y: u32) -> f64 {
+        self.stats[self.index(x, y)].error()
+    }
+    /// Returns an image showing how many samples were traced through each pixel, from black for none, through purple and orange, to yellow for the most.
+    /// Useful for checking where adaptive sampling spent its time.
+    pub fn sample_heatmap(&self) -> Film {
+        let max = self
+            .stats
+            .iter()
+            .map(|stats| stats.count)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let pixels = self
+            .stats
+            .iter()
+            .map(|stats| heatmap_color(stats.count as f64 / max as f64))
+            .collect();
+        Film::from_pixels(self.width, self.height, pixels)
+    }
+    /// Adds the samples from another film, which may only cover part of this one, like a tile from `Camera::render_region`.
+    /// AOV layers are matched up by name, and layers this film doesn't have yet are added.
+    /// Panics if the other film reaches outside of this one.
+    pub fn merge(&mut self, other: &Film) {
+        let layers: Vec<usize> = other.aovs.iter().map(|aov| self.add_layer(aov)).collect();
+        for y in 0..other.height {
+            for x in 0..other.width {
+                let from = other.index(x, y);
+                let to = self.index(
+                    x + other.origin.0 - self.origin.0,
+                    y + other.origin.1 - self.origin.1,
+                );
+                self.sums[to] += other.sums[from];
+                self.weights[to] += other.weights[from];
+                self.stats[to] = self.stats[to].combine(other.stats[from]);
+                for (from_layer, &to_layer) in layers.iter().enumerate() {
+                    self.aov_sums[to_layer][to] += other.aov_sums[from_layer][from];
+                }
+            }
+        }
+    }
+    /// Returns the AOVs this film has layers for.
+    pub fn aovs(&self) -> &[Aov] {
+        &self.aovs
+    }
+    /// Returns the layer of an AOV as its own film, or `None` if this film doesn't have it.
+    pub fn aov(&self, aov: &Aov) -> Option<Film> {
+        let layer = self.aovs.iter().position(|a| a == aov)?;
+        Some(Film {
+            width: self.width,
+            height: self.height,
+            origin: self.origin,
+            filter: self.filter,
+            sums: self.aov_sums[layer].clone(),
+            weights: self.weights.clone(),
+            stats: self.stats.clone(),
+            aovs: Vec::new(),
+            aov_sums: Vec::new(),
+        })
+    }
+    /// Returns every pixel, in rows from top to bottom.
+    pub fn pixels(&self) -> Vec<Color> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.pixel(x, y))
+            .collect()
+    }
+    /// Consumes the film, returning its pixels.
+    pub fn into_pixels(self) -> Vec<Color> {
+        self.pixels()
+    }
+    /// Encodes the film into 8 bit `[R, G, B, R, G, B, ...]` bytes with an [`OutputTransform`], the same as `Camera::render_to_bytes`.
+    pub fn to_rgb_bytes(&self, output: &OutputTransform) -> Vec<u8> {
+        self.pixels()
+            .into_iter()
+            .flat_map(|color| output.to_rgb_bytes(color))
+            .collect()
+    }
+
+    /// Writes everything the film has gathered, for a [`Checkpoint`](crate::checkpoint::Checkpoint). The AOV layers are written by name.
+    pub(crate) fn write_state(&self, w: &mut dyn Write) -> io::Result<()> {
+        for value in [self.width, self.height, self.origin.0, self.origin.1] {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        w.write_all(&[self.filter.tag()])?;
+        w.write_all(&self.filter.radius().to_le_bytes())?;
+
+        write_colors(w, &self.sums)?;
+        for weight in &self.weights {
+            w.write_all(&weight.to_le_bytes())?;
+        }
+        for stats in &self.stats {
+            w.write_all(&stats.count.to_le_bytes())?;
+            w.write_all(&stats.mean.to_le_bytes())?;
+            w.write_all(&stats.m2.to_le_bytes())?;
+        }
+        w.write_all(&(self.aovs.len() as u32).to_le_bytes())?;
+        for (aov, sums) in self.aovs.iter().zip(&self.aov_sums) {
+            write_string(w, aov.name())?;
+            write_colors(w, sums)?;
+        }
+        Ok(())
+    }
+    /// Reads a film written by `write_state`. Layers are matched by name with `aovs`, or with the built-in AOVs if they aren't in it.
+    pub(crate) fn read_state(r: &mut dyn Read, aovs: &[Aov]) -> Result<Film, CheckpointError> {
+        let (width, height) = (read_u32(r)?, read_u32(r)?);
+        let origin = (read_u32(r)?, read_u32(r)?);
+        let mut kind = [0];
+        r.read_exact(&mut kind)?;
+        let radius = read_f64(r)?;
+        let filter = Filter::from_tag(kind[0], radius)
+            .ok_or_else(|| CheckpointError::Format("unknown filter".to_string()))?;
+
+        // the size comes from the file, so nothing is allocated up front, a corrupt size just runs out of data
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .ok_or_else(|| CheckpointError::Format("film too big".to_string()))?;
+        let mut film = Film {
+            width,
+            height,
+            origin,
+            filter,
+            sums: read_colors(r, len)?,
+            weights: (0..len).map(|_| read_f64(r)).collect::<io::Result<_>>()?,
+            stats: (0..len)
+                .map(|_| {
+                    Ok(PixelStats {
+                        count: read_u32(r)?,
+                        mean: read_f64(r)?,
+                        m2: read_f64(r)?,
+                    })
+                })
+                .collect::<io::Result<_>>()?,
+            aovs: Vec::new(),
+            aov_sums: Vec::new(),
+        };
+        for _ in 0..read_u32(r)? {
+            let name = read_string(r)?;
+            let aov = aovs
+                .iter()
+                .find(|aov| aov.name() == name)
+                .cloned()
+                .or_else(|| Aov::builtin(&name))
+                .ok_or(CheckpointError::UnknownAov(name))?;
+            film.aovs.push(aov);
+            film.aov_sums.push(read_colors(r, len)?);
+        }
+        Ok(film)
+    }
+
+    /// Returns the index of the layer for `aov`, adding an empty one if there isn't one yet.
+    fn add_layer(&mut self, aov: &Aov) -> usize {
+        if let Some(layer) = self.aovs.iter().position(|a| a == aov) {
+            return layer;
+        }
+        self.aovs.push(aov.clone());
+        self.aov_sums.push(vec![Color::from(0.0); self.sums.len()]);
+        self.aovs.len() - 1
+    }
+
+    /// The index of pixel (`x`, `y`) of the full image, if it's inside this film.
+    fn local_index(&self, x: u32, y: u32) -> Option<usize> {
+        let (x, y) = (x.checked_sub(self.origin.0)?, y.checked_sub(self.origin.1)?);
+        (x < self.width && y < self.height).then(|| self.index(x, y))
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height, "pixel outside the film");
+        y as usize * self.width as usize + x as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// The running count, mean and variance of the brightness of the samples in a pixel, using Welford's algorithm.
+pub(crate) struct PixelStats {
+    count: u32,
+    mean: f64,
+    /// The sum of squared differences from the mean
+    m2: f64,
+}
+
+impl PixelStats {
+    /// Adds a sample's color to the statistics.
+    pub(crate) fn add(&mut self, color: Color) {
+        let luminance = color.luminance().max(0.0);
+        // fireflies would swamp the variance otherwise, so the brightness is squashed into 0-1
+        let value = if luminance.is_finite() {
+            luminance / (1.0 + luminance)
+        } else {
+            1.0
+        };
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+    /// Combines the statistics of two separate sets of samples, with Chan's formula.
+    pub(crate) fn combine(self, other: PixelStats) -> PixelStats {
+        let count = self.count + other.count;
+        if count == 0 {
+            return PixelStats::default();
+        }
+        let delta = other.mean - self.mean;
+        let (a, b) = (self.count as f64, other.count as f64);
+        PixelStats {
+            count,
+            mean: self.mean + delta * b / count as f64,
+            m2: self.m2 + other.m2 + delta * delta * a * b / count as f64,
+        }
+    }
+    /// The number of samples.
+    pub(crate) fn count(self) -> u32 {
+        self.count
+    }
+    /// The standard error of the mean, how far off the pixel's brightness is likely to be.
+    pub(crate) fn error(self) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+        let n = self.count as f64;
+        (self.m2 / (n - 1.0) / n).sqrt()
+    }
+}
+
+fn write_colors(w: &mut dyn Write, colors: &[Color]) -> io::Result<()> {
+    for color in colors {
+        for value in [color.x, color.y, color.z] {
+            w.write_all(&value.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn read_colors(r: &mut dyn Read, len: usize) -> io::Result<Vec<Color>> {
+    (0..len)
+        .map(|_| Ok(Color::new(read_f64(r)?, read_f64(r)?, read_f64(r)?)))
+        .collect()
+}
+
+/// Maps 0-1 to a color on a ramp similar to the "inferno" colormap, as a linear color.
+fn heatmap_color(t: f64) -> Color {
+    const STOPS: [[f64; 3]; 5] = [
+        [0.0, 0.0, 0.016],
+        [0.341, 0.063, 0.431],
+        [0.733, 0.216, 0.329],
+        [0.976, 0.557, 0.035],
+        [0.988, 1.0, 0.643],
+    ];
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let i = (t as usize).min(STOPS.len() - 2);
+    let f = t - i as f64;
+    // the stops are sRGB, so they are blended as sRGB and decoded after
+    let channel = |c: usize| srgb_to_linear(STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * f);
+    Color::new(channel(0), channel(1), channel(2))
+}
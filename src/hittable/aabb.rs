@@ -0,0 +1,79 @@
+//! A module for the `Aabb` struct, an axis-aligned bounding box.
+//! Bounding boxes are used by the [`BvhNode`](super::bvh::BvhNode) to quickly skip objects a ray can't possibly hit.
+use std::ops::Range;
+
+use crate::{ray::Ray, vec3::*};
+
+#[derive(Clone, Copy, Debug)]
+/// An axis-aligned bounding box, stored as its minimum and maximum corners.
+pub struct Aabb {
+    /// The corner of the box with the smallest x, y and z values
+    pub min: Point3,
+    /// The corner of the box with the largest x, y and z values
+    pub max: Point3,
+}
+
+impl Aabb {
+    /// Creates a new `Aabb` spanning two corner points, the points can be given in any order.
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Aabb {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+    /// Creates the smallest `Aabb` that contains both `a` and `b`.
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Self {
+        Aabb::new(
+            Point3::new(
+                a.min.x.min(b.min.x),
+                a.min.y.min(b.min.y),
+                a.min.z.min(b.min.z),
+            ),
+            Point3::new(
+                a.max.x.max(b.max.x),
+                a.max.y.max(b.max.y),
+                a.max.z.max(b.max.z),
+            ),
+        )
+    }
+    /// Returns the center point of the box.
+    pub fn centroid(&self) -> Point3 {
+        (self.min + self.max) * 0.5
+    }
+    /// Returns the index of the longest axis of the box, 0 for x, 1 for y and 2 for z.
+    pub fn longest_axis(&self) -> usize {
+        let size = self.max - self.min;
+        if size.x > size.y && size.x > size.z {
+            0
+        } else if size.y > size.z {
+            1
+        } else {
+            2
+        }
+    }
+    /// Returns the surface area of the box, used to estimate the cost of splits in the BVH.
+    pub fn surface_area(&self) -> f64 {
+        let size = self.max - self.min;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+    /// Checks if a ray passes through the box anywhere in the `ray_t` range, using the slab method.
+    pub fn hit(&self, r: &Ray, ray_t: Range<f64>) -> bool {
+        let mut t_min = ray_t.start;
+        let mut t_max = ray_t.end;
+
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.direction[axis];
+            let mut t0 = (self.min[axis] - r.origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - r.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,239 @@
+//! A module for the `BvhNode` struct, a bounding volume hierarchy.
+//! A BVH sorts the objects of a scene into a tree of nested bounding boxes, so a ray only has to be tested against the few objects whose boxes it actually passes through, instead of every object in the scene.
+//! # Example
+//! ```
+//! use rtwlib::{color::Color, hittable::{bvh::BvhNode, sphere::Sphere, HittableList}, material::Lambertian, vec3::Point3};
+//! use std::sync::Arc;
+//!
+//! let mut objects = HittableList::new();
+//! let material = Arc::new(Lambertian::new(Color::from(0.5)));
+//! for i in 0..100 {
+//!     objects.add(Sphere::new(Point3::new(i, 0, -5), 0.4, material.clone()));
+//! }
+//!
+//! let mut world = HittableList::new();
+//! world.add(BvhNode::new(objects)); // the camera traces against the BVH like any other object
+//! ```
+use std::ops::Range;
+
+use super::{aabb::Aabb, hit_with_id, HitRecord, Hittable, HittableList};
+use crate::{
+    ray::Ray,
+    scene::{MaterialTable, ObjectDescription},
+};
+
+/// An object waiting to be sorted into the tree: its bounding box, its id, and the object.
+type Entry = (Aabb, (u32, Box<dyn Hittable>));
+
+/// The number of buckets object centroids are sorted into when looking for the cheapest split.
+const SAH_BUCKETS: usize = 12;
+
+#[derive(Clone)]
+/// A bounding volume hierarchy, built from a [`HittableList`].
+/// Bounded objects are split into a tree using the surface area heuristic, objects without a bounding box (like a [`Plane`](super::plane::Plane)) can't be sorted, and are tested against every ray.
+pub struct BvhNode {
+    root: Option<BvhTree>,
+    unbounded: Vec<(u32, Box<dyn Hittable>)>,
+    len: usize,
+}
+
+#[derive(Clone)]
+/// Objects keep their position in the list the BVH was built from, as their `object_id`.
+enum BvhTree {
+    Leaf(u32, Box<dyn Hittable>),
+    Branch {
+        bbox: Aabb,
+        left: Box<BvhTree>,
+        right: Box<BvhTree>,
+    },
+}
+
+impl BvhNode {
+    /// Builds a new `BvhNode` from the objects in a `HittableList`.
+    pub fn new(list: HittableList) -> Self {
+        let len = list.objects.len();
+        let mut bounded = Vec::new();
+        let mut unbounded = Vec::new();
+
+        for (id, object) in list.objects.into_iter().enumerate() {
+            let object = (id as u32, object);
+            match object.1.bounding_box() {
+                Some(bbox) => bounded.push((bbox, object)),
+                None => unbounded.push(object),
+            }
+        }
+
+        BvhNode {
+            root: BvhTree::build(bounded),
+            unbounded,
+            len,
+        }
+    }
+}
+
+impl BvhTree {
+    fn build(mut objects: Vec<Entry>) -> Option<BvhTree> {
+        match objects.len() {
+            0 => None,
+            1 => objects
+                .pop()
+                .map(|(_, (id, object))| BvhTree::Leaf(id, object)),
+            _ => {
+                let bbox = objects
+                    .iter()
+                    .skip(1)
+                    .fold(objects[0].0, |acc, (b, _)| Aabb::surrounding(&acc, b));
+                let right = split(&mut objects);
+
+                Some(BvhTree::Branch {
+                    bbox,
+                    left: Box::new(BvhTree::build(objects)?),
+                    right: Box::new(BvhTree::build(right)?),
+                })
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self {
+            BvhTree::Leaf(_, object) => object.bounding_box(),
+            BvhTree::Branch { bbox, .. } => Some(*bbox),
+        }
+    }
+
+    /// Calls `f` on every object in the tree.
+    fn for_each<'a>(&'a self, f: &mut impl FnMut(&'a dyn Hittable)) {
+        match self {
+            BvhTree::Leaf(_, object) => f(object.as_ref()),
+            BvhTree::Branch { left, right, .. } => {
+                left.for_each(f);
+                right.for_each(f);
+            }
+        }
+    }
+
+    fn hit(&self, r: &Ray, ray_t: Range<f64>, rec: &mut HitRecord) -> bool {
+        match self {
+            BvhTree::Leaf(id, object) => hit_with_id(object.as_ref(), *id, r, ray_t, rec),
+            BvhTree::Branch { bbox, left, right } => {
+                if !bbox.hit(r, ray_t.clone()) {
+                    return false;
+                }
+                let hit_left = left.hit(r, ray_t.clone(), rec);
+                let end = if hit_left { rec.t } else { ray_t.end };
+                let hit_right = right.hit(r, ray_t.start..end, rec);
+                hit_left || hit_right
+            }
+        }
+    }
+}
+
+/// Splits the objects in two using the surface area heuristic, along the longest axis of their centroids.
+/// The first half stays in `objects`, and the second half is returned. Falls back to splitting at the median when every centroid is in the same spot.
+fn split(objects: &mut Vec<Entry>) -> Vec<Entry> {
+    let first = objects[0].0.centroid();
+    let centroid_bounds = objects.iter().fold(Aabb::new(first, first), |acc, (b, _)| {
+        Aabb::surrounding(&acc, &Aabb::new(b.centroid(), b.centroid()))
+    });
+    let axis = centroid_bounds.longest_axis();
+    let (low, high) = (centroid_bounds.min[axis], centroid_bounds.max[axis]);
+
+    objects.sort_by(|a, b| a.0.centroid()[axis].total_cmp(&b.0.centroid()[axis]));
+    if high - low <= f64::EPSILON {
+        let mid = objects.len() / 2;
+        return objects.split_off(mid);
+    }
+
+    // sort centroids into buckets along the axis, and pick the bucket boundary with the lowest cost
+    let bucket = |b: &Aabb| {
+        let offset = (b.centroid()[axis] - low) / (high - low);
+        ((offset * SAH_BUCKETS as f64) as usize).min(SAH_BUCKETS - 1)
+    };
+    let mut counts = [0usize; SAH_BUCKETS];
+    let mut bounds: [Option<Aabb>; SAH_BUCKETS] = [None; SAH_BUCKETS];
+    for (b, _) in objects.iter() {
+        let i = bucket(b);
+        counts[i] += 1;
+        bounds[i] = Some(bounds[i].map_or(*b, |acc| Aabb::surrounding(&acc, b)));
+    }
+
+    let mut best = (f64::INFINITY, objects.len() / 2);
+    for boundary in 1..SAH_BUCKETS {
+        let (left_count, left_area) = bucket_cost(&counts[..boundary], &bounds[..boundary]);
+        let (right_count, right_area) = bucket_cost(&counts[boundary..], &bounds[boundary..]);
+        if left_count == 0 || right_count == 0 {
+            continue;
+        }
+        let cost = left_count as f64 * left_area + right_count as f64 * right_area;
+        if cost < best.0 {
+            best = (cost, left_count);
+        }
+    }
+    objects.split_off(best.1)
+}
+
+/// Returns the number of objects in a range of buckets, and the surface area of their combined bounds.
+fn bucket_cost(counts: &[usize], bounds: &[Option<Aabb>]) -> (usize, f64) {
+    let total = counts.iter().sum();
+    let area = bounds
+        .iter()
+        .flatten()
+        .fold(None, |acc: Option<Aabb>, b| {
+            Some(acc.map_or(*b, |acc| Aabb::surrounding(&acc, b)))
+        })
+        .map_or(0.0, |b| b.surface_area());
+    (total, area)
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, ray_t: Range<f64>, rec: &mut HitRecord) -> bool {
+        let mut hit_anything = false;
+        let mut closest_so_far = ray_t.end;
+
+        if let Some(root) = &self.root {
+            if root.hit(r, ray_t.start..closest_so_far, rec) {
+                hit_anything = true;
+                closest_so_far = rec.t;
+            }
+        }
+        for (id, object) in self.unbounded.iter() {
+            if hit_with_id(object.as_ref(), *id, r, ray_t.start..closest_so_far, rec) {
+                hit_anything = true;
+                closest_so_far = rec.t;
+            }
+        }
+        hit_anything
+    }
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.root.as_ref().and_then(|root| root.bounding_box())
+        } else {
+            None
+        }
+    }
+    fn collect_lights(&self, lights: &mut Vec<Box<dyn Hittable>>) {
+        if let Some(root) = &self.root {
+            root.for_each(&mut |object| object.collect_lights(lights));
+        }
+        for (_, object) in self.unbounded.iter() {
+            object.collect_lights(lights);
+        }
+    }
+    fn describe(&self, materials: &mut MaterialTable) -> Option<ObjectDescription> {
+        let mut objects = Vec::new();
+        if let Some(root) = &self.root {
+            root.for_each(&mut |object| objects.push(object.describe(materials)));
+        }
+        objects.extend(
+            self.unbounded
+                .iter()
+                .map(|(_, object)| object.describe(materials)),
+        );
+        Some(ObjectDescription::Bvh {
+            objects: objects.into_iter().collect::<Option<_>>()?,
+        })
+    }
+    fn as_string(&self) -> String {
+        format!("[ BVH ] Objects: {}", self.len)
+    }
+}
@@ -0,0 +1,203 @@
+//! A [`Hittable`] object, Mesh. Contains the [`Hittable`] trait implementation for Mesh.
+//! A `Mesh` is a set of triangles that share one list of vertices, which is a lot smaller than storing every [`Triangle`](super::triangle::Triangle) separately.
+//! The triangles are sorted into a [`BvhNode`] when the mesh is created, so large models render quickly.
+//! # Example
+//! ```
+//! use rtwlib::{color::Color, hittable::{mesh::{Mesh, MeshData}, HittableList}, material::Lambertian, vec3::Point3};
+//! use std::sync::Arc;
+//!
+//! // a square made of two triangles
+//! let data = MeshData {
+//!     positions: vec![
+//!         Point3::new(-1, -1, -2),
+//!         Point3::new(1, -1, -2),
+//!         Point3::new(1, 1, -2),
+//!         Point3::new(-1, 1, -2),
+//!     ],
+//!     indices: vec![[0, 1, 2], [0, 2, 3]],
+//!     ..Default::default()
+//! };
+//!
+//! let mut world = HittableList::new();
+//! world.add(Mesh::new(data, Arc::new(Lambertian::new(Color::from(0.5)))));
+//! ```
+use std::{ops::Range, sync::Arc};
+
+use super::{aabb::Aabb, bvh::BvhNode, triangle, HitRecord, Hittable, HittableList};
+use crate::{
+    material::Material,
+    ray::Ray,
+    sampler::Sampler,
+    scene::{MaterialTable, ObjectDescription},
+    vec3::*,
+};
+
+#[derive(Clone, Debug, Default)]
+/// The vertex and index buffers of a [`Mesh`].
+/// Every vertex has a position, and optionally a normal and UV coordinate, at the same index in their lists.
+pub struct MeshData {
+    /// The position of each vertex
+    pub positions: Vec<Point3>,
+    /// The normal of each vertex, leave this empty for flat shading
+    pub normals: Vec<Vec3>,
+    /// The UV coordinate of each vertex, leave this empty to use barycentric coordinates
+    pub uvs: Vec<(f64, f64)>,
+    /// The three vertex indices of each triangle, counter-clockwise from the front
+    pub indices: Vec<[usize; 3]>,
+}
+
+impl MeshData {
+    /// Returns the number of triangles in the mesh.
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+    /// Checks that every index is in range, and that the normals and UVs are either empty or the same length as the positions.
+    /// Returns what's wrong if they aren't.
+    pub fn validate(&self) -> Result<(), String> {
+        let vertex_count = self.positions.len();
+        if let Some(&index) = self.indices.iter().flatten().find(|&&i| i >= vertex_count) {
+            return Err(format!(
+                "mesh index {} out of range, there are {} vertices",
+                index, vertex_count
+            ));
+        }
+        if !self.normals.is_empty() && self.normals.len() != vertex_count {
+            return Err("mesh needs one normal per vertex".to_string());
+        }
+        if !self.uvs.is_empty() && self.uvs.len() != vertex_count {
+            return Err("mesh needs one uv per vertex".to_string());
+        }
+        Ok(())
+    }
+    /// Returns the positions of the vertices of triangle `face`.
+    pub fn triangle(&self, face: usize) -> [Point3; 3] {
+        self.indices[face].map(|i| self.positions[i])
+    }
+}
+
+/// The buffers and material, shared between the mesh and every triangle in its BVH.
+struct SharedMesh {
+    data: MeshData,
+    mat: Arc<dyn Material>,
+}
+
+#[derive(Clone)]
+/// A triangle mesh, with one material.
+pub struct Mesh {
+    shared: Arc<SharedMesh>,
+    bvh: BvhNode,
+}
+
+/// A single triangle of a mesh, refering back to the shared buffers.
+#[derive(Clone)]
+struct MeshTriangle {
+    mesh: Arc<SharedMesh>,
+    face: usize,
+}
+
+impl Mesh {
+    /// Creates a new `Mesh` from its buffers and a material.
+    /// Panics if the buffers don't fit together, see [`MeshData::validate`].
+    pub fn new(data: MeshData, mat: Arc<dyn Material>) -> Self {
+        if let Err(problem) = data.validate() {
+            panic!("{}", problem);
+        }
+
+        let shared = Arc::new(SharedMesh { data, mat });
+        let mut triangles = HittableList::new();
+        for face in 0..shared.data.triangle_count() {
+            triangles.add(MeshTriangle {
+                mesh: Arc::clone(&shared),
+                face,
+            });
+        }
+
+        Mesh {
+            bvh: BvhNode::new(triangles),
+            shared,
+        }
+    }
+    /// Returns the buffers of the mesh.
+    pub fn data(&self) -> &MeshData {
+        &self.shared.data
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, ray_t: Range<f64>, rec: &mut HitRecord) -> bool {
+        let data = &self.mesh.data;
+        let vertices = data.triangle(self.face);
+
+        match triangle::intersect(&vertices, r, &ray_t) {
+            Some(hit) => {
+                let [i, j, k] = data.indices[self.face];
+                let normals = (!data.normals.is_empty())
+                    .then(|| [data.normals[i], data.normals[j], data.normals[k]]);
+                let uvs = (!data.uvs.is_empty()).then(|| [data.uvs[i], data.uvs[j], data.uvs[k]]);
+                triangle::set_hit_record(rec, r, hit, &vertices, normals, uvs, &self.mesh.mat);
+                true
+            }
+            None => false,
+        }
+    }
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(triangle::bounding_box(&self.mesh.data.triangle(self.face)))
+    }
+    fn is_light(&self) -> bool {
+        self.mesh.mat.is_emissive()
+    }
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        triangle::pdf_value(&self.mesh.data.triangle(self.face), origin, direction)
+    }
+    fn random_direction(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        triangle::random_direction(&self.mesh.data.triangle(self.face), origin, sampler)
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, r: &Ray, ray_t: Range<f64>, rec: &mut HitRecord) -> bool {
+        // the triangles are one object, so they keep the mesh's id instead of their own
+        let id = rec.object_id;
+        let hit = self.bvh.hit(r, ray_t, rec);
+        rec.object_id = id;
+        hit
+    }
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounding_box()
+    }
+    fn collect_lights(&self, lights: &mut Vec<Box<dyn Hittable>>) {
+        // each emissive triangle is sampled on its own
+        self.bvh.collect_lights(lights);
+    }
+    fn describe(&self, materials: &mut MaterialTable) -> Option<ObjectDescription> {
+        let data = &self.shared.data;
+        Some(ObjectDescription::Mesh {
+            positions: data.positions.clone(),
+            normals: data.normals.clone(),
+            uvs: data.uvs.clone(),
+            indices: data.indices.clone(),
+            material: materials.name(&self.shared.mat)?,
+        })
+    }
+    fn as_string(&self) -> String {
+        format!(
+            "[ Mesh ] Triangles: {}, Vertices: {}, material: {:?}",
+            self.shared.data.triangle_count(),
+            self.shared.data.positions.len(),
+            self.shared.mat
+        )
+    }
+    fn as_info_vec(&self) -> Vec<String> {
+        let center = self
+            .bounding_box()
+            .map_or(Point3::from(0.), |b| b.centroid());
+        vec![
+            "Mesh".to_string(),
+            self.shared.data.triangle_count().to_string(),
+            center.x.to_string(),
+            center.y.to_string(),
+            center.z.to_string(),
+            format!("{:?}", self.shared.mat),
+        ]
+    }
+}
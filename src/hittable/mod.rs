@@ -3,16 +3,24 @@
 //! Any hittable object must implement the `Hittable` trait, which requires the `hit` function to be implemented, which determines if a ray hits the object.
 //! The `HittableList` struct is a collection of hittable objects, and implements the `Hittable` trait itself, allowing for nested collections of objects ( I dont see why you would need that ).
 //!
+//! A `HittableList` tests every object for every ray, which gets slow for large scenes. [`HittableList::into_bvh`] sorts the objects into a [`bvh::BvhNode`], which only tests the objects a ray can actually reach.
+pub mod aabb;
+pub mod bvh;
+pub mod mesh;
 pub mod plane;
 pub mod sphere;
+pub mod triangle;
+use self::{aabb::Aabb, bvh::BvhNode};
 use crate::{
     color::Color,
     material::{Lambertian, Material},
     ray::Ray,
+    sampler::Sampler,
+    scene::{MaterialTable, ObjectDescription},
     vec3::*,
 };
 
-use std::{ops::Range, rc::Rc};
+use std::{ops::Range, sync::Arc};
 
 /// A `HitRecord` is a struct that contains information about a hit, such as the hit point, normal, material, and other information.
 pub struct HitRecord {
@@ -21,27 +29,38 @@ pub struct HitRecord {
     /// The normal vector at the hit point
     pub normal: Vec3,
     /// The material of the object that was hit
-    pub mat: Rc<dyn Material>,
+    pub mat: Arc<dyn Material>,
     /// The distance along the ray that the hit was
     pub t: f64,
     /// A boolean indicating if the hit was on the front face of the object
     pub front_face: bool,
+    /// The U surface coordinate of the hit, used to look up textures
+    pub u: f64,
+    /// The V surface coordinate of the hit, used to look up textures
+    pub v: f64,
+    /// The position of the object that was hit in the [`HittableList`] ( or [`BvhNode`] ) it was added to, used to tell objects apart in debug renders.
+    /// Objects in nested lists get their position in the innermost list, and every triangle of a mesh shares the mesh's id.
+    pub object_id: u32,
 }
 /// A `HittableList` is a struct that contains a list of `Hittable` objects, and implements the `Hittable` trait itself. Mostly useful to quickly test all objects in a scene for hits. Use it for scenes. idk
 /// # Example
 /// ```
-/// use rtwlib::hittable::HittableList;
-/// use rtwlib::sphere::Sphere;
+/// use rtwlib::hittable::{sphere::Sphere, HittableList};
 /// use rtwlib::vec3::Point3;
+/// use rtwlib::color::Color;
 /// use rtwlib::material::Lambertian;
+/// use std::sync::Arc;
 ///
 /// let mut world = HittableList::new();
-/// let material = Rc::new(Lambertian::new(Color::from(0.3)));
+/// let material = Arc::new(Lambertian::new(Color::from(0.3)));
 /// let sphere = Sphere::new(Point3::from(0.0), 0.5, material);
 /// world.add(sphere);
+/// ```
 pub struct HittableList {
     /// A list of hittable objects, stored on the heap
-    pub objects: Vec<Box<dyn Hittable>>,
+    objects: Vec<Box<dyn Hittable>>,
+    /// The objects that give off light, which the camera aims extra rays at. Kept in step with `objects` by [`HittableList::add`] and [`HittableList::remove`].
+    lights: Vec<Box<dyn Hittable>>,
 }
 
 impl HitRecord {
@@ -55,7 +74,7 @@ impl HitRecord {
         }
     }
     /// Sets the material of the hit record
-    pub fn set_material(&mut self, mat: Rc<dyn Material>) {
+    pub fn set_material(&mut self, mat: Arc<dyn Material>) {
         self.mat = mat;
     }
 }
@@ -65,9 +84,12 @@ impl Default for HitRecord {
         HitRecord {
             p: Point3::from(0.0),
             normal: Vec3::from(0.0),
-            mat: Rc::new(Lambertian::new(Color::from(0.5))),
+            mat: Arc::new(Lambertian::new(Color::from(0.5))),
             t: 0.0,
             front_face: false,
+            u: 0.0,
+            v: 0.0,
+            object_id: 0,
         }
     }
 }
@@ -77,11 +99,64 @@ impl HittableList {
     pub fn new() -> Self {
         HittableList {
             objects: Vec::new(),
+            lights: Vec::new(),
         }
     }
-    /// Adds an object to the `HittableList`.
+    /// Adds an object to the `HittableList`. If it is a light, or has lights inside it, they are also added to [`HittableList::lights`].
     pub fn add<T: Hittable + 'static>(&mut self, object: T) {
-        self.objects.push(Box::new(object));
+        self.add_boxed(Box::new(object));
+    }
+    /// Adds an already boxed object to the `HittableList`, finding its lights like [`HittableList::add`].
+    pub fn add_boxed(&mut self, object: Box<dyn Hittable>) {
+        object.collect_lights(&mut self.lights);
+        self.objects.push(object);
+    }
+    /// Removes and returns the object at `index`, along with its lights.
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Box<dyn Hittable> {
+        let object = self.objects.remove(index);
+        self.lights.clear();
+        for object in &self.objects {
+            object.collect_lights(&mut self.lights);
+        }
+        object
+    }
+    /// Returns the objects in the list, in the order they were added.
+    pub fn objects(&self) -> &[Box<dyn Hittable>] {
+        &self.objects
+    }
+    /// Returns the objects that give off light, which the camera aims extra rays at.
+    pub fn lights(&self) -> &[Box<dyn Hittable>] {
+        &self.lights
+    }
+    /// Sorts the objects into a [`BvhNode`], and returns a new `HittableList` containing only that node.
+    /// The scene renders the same, but large scenes render much faster. The lights are found again inside the node.
+    pub fn into_bvh(self) -> HittableList {
+        let mut list = HittableList::new();
+        list.add(BvhNode::new(self));
+        list
+    }
+    /// Returns the probability density of [`HittableList::sample_light`] picking `direction` from `origin`, per unit of solid angle.
+    /// Returns 0.0 if there are no lights, or the direction misses all of them.
+    pub fn light_pdf(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = self
+            .lights
+            .iter()
+            .map(|light| light.pdf_value(origin, direction))
+            .sum();
+        total / self.lights.len() as f64
+    }
+    /// Picks a random light, and returns a random direction from `origin` towards it. Returns `None` if there are no lights.
+    pub fn sample_light(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        if self.lights.is_empty() {
+            return None;
+        }
+        let pick = (sampler.next_1d() * self.lights.len() as f64) as usize;
+        let light = &self.lights[pick.min(self.lights.len() - 1)];
+        Some(light.random_direction(origin, sampler))
     }
     /// Returns a vector of strings representing the objects in the list.
     pub fn as_simple_vec(&self) -> Vec<String> {
@@ -109,9 +184,12 @@ impl Hittable for HittableList {
         let mut hit_anything = false;
         let mut closest_so_far = ray_t.end;
 
-        for object in self.objects.iter() {
+        for (id, object) in self.objects.iter().enumerate() {
             //checks every object for a hit
-            let mut temp_rec: HitRecord = Default::default();
+            let mut temp_rec = HitRecord {
+                object_id: id as u32,
+                ..Default::default()
+            };
 
             if object.hit(r, ray_t.start..closest_so_far, &mut temp_rec) {
                 hit_anything = true;
@@ -121,13 +199,80 @@ impl Hittable for HittableList {
         }
         return hit_anything;
     }
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut boxes = self.objects.iter().map(|object| object.bounding_box());
+        let first = boxes.next()??;
+        boxes.try_fold(first, |acc, bbox| Some(Aabb::surrounding(&acc, &bbox?)))
+    }
+    fn collect_lights(&self, lights: &mut Vec<Box<dyn Hittable>>) {
+        for object in &self.objects {
+            object.collect_lights(lights);
+        }
+    }
+    fn describe(&self, materials: &mut MaterialTable) -> Option<ObjectDescription> {
+        Some(ObjectDescription::List {
+            objects: self
+                .objects
+                .iter()
+                .map(|object| object.describe(materials))
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+/// Tests `object` for a hit, setting the record's `object_id` to `id` if it is hit.
+/// Objects can set their own id over it, so the innermost list an object is in picks its id.
+pub(crate) fn hit_with_id(
+    object: &dyn Hittable,
+    id: u32,
+    r: &Ray,
+    ray_t: Range<f64>,
+    rec: &mut HitRecord,
+) -> bool {
+    let previous = rec.object_id;
+    rec.object_id = id;
+    if object.hit(r, ray_t, rec) {
+        return true;
+    }
+    rec.object_id = previous;
+    false
 }
 /// The `Hittable` trait is used to define objects that can be hit by rays, it would be implented by any object in a scene like a Sphere or Cube.
-pub trait Hittable: HittableClone {
+/// Hittables must be `Send + Sync`, as the camera shares the scene between its render threads.
+pub trait Hittable: HittableClone + Send + Sync {
     /// Determines if a ray hits the object, and modifies a [`HitRecord`] if it does.
     fn hit(&self, _r: &Ray, _ray_t: Range<f64>, _rec: &mut HitRecord) -> bool {
         false
     }
+    /// Returns the axis-aligned box the object fits inside, or `None` if the object is unbounded ( like an infinite plane ).
+    /// This is used to sort objects into a [`BvhNode`], unbounded objects still work, but they are tested against every ray.
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+    /// Describes the object, so it can be saved in a scene file. Materials are named through the `materials` table.
+    /// Returns `None` if the object or its material can't be described.
+    fn describe(&self, _materials: &mut MaterialTable) -> Option<ObjectDescription> {
+        None
+    }
+    /// Returns true if the object gives off light, and can be aimed at with `pdf_value` and `random_direction`.
+    /// Defaults to false, the premade objects return true if they have an emissive material and can be sampled.
+    fn is_light(&self) -> bool {
+        false
+    }
+    /// Adds the lights in the object to `lights`, used by [`HittableList::add`].
+    /// Defaults to adding the object itself if `is_light` returns true, objects made of other objects ( like a [`BvhNode`] ) add the lights inside them instead.
+    fn collect_lights(&self, lights: &mut Vec<Box<dyn Hittable>>) {
+        if self.is_light() {
+            lights.push(self.clone_box());
+        }
+    }
+    /// Returns the probability density of `random_direction` picking `direction` from `origin`, per unit of solid angle, or 0.0 if the direction misses the object.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+    /// Returns a random direction from `origin` towards the object, used to send rays straight at lights.
+    fn random_direction(&self, _origin: &Point3, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(1., 0., 0.)
+    }
     /// Returns a string representation of the object.
     fn as_string(&self) -> String {
         "Hittable".to_string()
@@ -162,6 +307,7 @@ impl Clone for HittableList {
     fn clone(&self) -> Self {
         HittableList {
             objects: self.objects.iter().map(|obj| obj.clone()).collect(),
+            lights: self.lights.clone(),
         }
     }
 }
@@ -2,10 +2,13 @@
 //! A `Plane` is an infinite plane, defined by an origin point, a normal vector, and a material.
 //! The normal vector is the direction that the plane faces.
 use std::ops::Range;
-use std::rc::Rc;
+use std::sync::Arc;
 
+use crate::scene::{MaterialTable, ObjectDescription};
 use crate::utils::RangeExtensions;
 
+use super::aabb::Aabb;
+use super::cross;
 use super::dot;
 use super::HitRecord;
 use super::Hittable;
@@ -22,12 +25,12 @@ pub struct Plane {
     /// The normal vector of the plane
     pub normal: Vec3,
     /// The material of the plane
-    pub mat: Rc<dyn Material>,
+    pub mat: Arc<dyn Material>,
 }
 
 impl Plane {
     /// Creates a new `Plane` with the given origin, normal and material.
-    pub fn new(origin: Vec3, normal: Vec3, mat: Rc<dyn Material>) -> Self {
+    pub fn new(origin: Vec3, normal: Vec3, mat: Arc<dyn Material>) -> Self {
         Plane {
             origin,
             normal: normal.normalized(),
@@ -36,6 +39,23 @@ impl Plane {
     }
 }
 
+impl Plane {
+    /// Returns the `u`, `v` coordinates of a point on the plane, measured in world units from the origin.
+    /// The coordinates aren't limited to 0-1, so textures repeat every unit.
+    fn uv(&self, p: &Vec3) -> (f64, f64) {
+        // pick any axis that isn't parallel to the normal to build the tangent from
+        let helper = if self.normal.x.abs() > 0.9 {
+            Vec3::new(0., 1., 0.)
+        } else {
+            Vec3::new(1., 0., 0.)
+        };
+        let tangent = cross(&helper, &self.normal).normalized();
+        let bitangent = cross(&self.normal, &tangent);
+        let offset = *p - self.origin;
+        (dot(&offset, &tangent), dot(&offset, &bitangent))
+    }
+}
+
 impl Hittable for Plane {
     fn hit(&self, r: &crate::ray::Ray, ray_t: Range<f64>, rec: &mut HitRecord) -> bool {
         // this took me like 2 hours, dont screw around with it too much.
@@ -46,12 +66,23 @@ impl Hittable for Plane {
                 rec.t = t;
                 rec.p = r.at(t);
                 rec.set_face_normal(r, &self.normal);
-                rec.set_material(Rc::clone(&self.mat));
+                (rec.u, rec.v) = self.uv(&rec.p);
+                rec.set_material(Arc::clone(&self.mat));
                 return true;
             }
         }
         false
     }
+    fn bounding_box(&self) -> Option<Aabb> {
+        None // planes are infinite, so they can't be bounded
+    }
+    fn describe(&self, materials: &mut MaterialTable) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Plane {
+            origin: self.origin,
+            normal: self.normal,
+            material: materials.name(&self.mat)?,
+        })
+    }
     fn as_string(&self) -> String {
         format!(
             "[ Plane ] Normal: ({}, {}, {}), Position: ({}x, {}z, {}z), material: {:?}",
@@ -1,23 +1,26 @@
 //! A [`Hittable`] object, Sphere. Contains the [`Hittable`] trait implementation for Sphere.
 
 use crate::{
-    hittable::{HitRecord, Hittable},
+    hittable::{aabb::Aabb, HitRecord, Hittable},
     material::Material,
+    ray::Ray,
+    sampler::Sampler,
+    scene::{MaterialTable, ObjectDescription},
     utils::RangeExtensions,
     vec3::*,
 };
-use std::{ops::Range, rc::Rc};
+use std::{f64::consts::PI, ops::Range, sync::Arc};
 #[derive(Clone)]
 
 /// A `Sphere` is a struct that represents a sphere in 3D space. It has a center, radius, and pointer to a material.
 pub struct Sphere {
     center: Point3,
     radius: f64,
-    mat: Rc<dyn Material>,
+    mat: Arc<dyn Material>,
 }
 impl Sphere {
     /// Creates a new `Sphere` with the given center, radius, and material.
-    pub fn new(center: Point3, radius: f64, mat: Rc<dyn Material>) -> Self {
+    pub fn new(center: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
         Sphere {
             center,
             radius: f64::max(radius, 0.0),
@@ -26,8 +29,31 @@ impl Sphere {
     }
 }
 
+/// Returns the `u`, `v` coordinates of a point on the unit sphere.
+/// `u` goes around the Y axis starting from -X, and `v` goes from the bottom of the sphere to the top, both from 0 to 1.
+fn sphere_uv(p: &Point3) -> (f64, f64) {
+    let theta = (-p.y).acos();
+    let phi = (-p.z).atan2(p.x) + std::f64::consts::PI;
+
+    (
+        phi / (2.0 * std::f64::consts::PI),
+        theta / std::f64::consts::PI,
+    )
+}
+
+/// Returns the solid angle of the cone of directions that hit a sphere, seen from `dist_squared` away from its center, or `None` from inside the sphere.
+fn cone_size(radius: f64, dist_squared: f64) -> Option<f64> {
+    let sin_squared = radius * radius / dist_squared;
+    if sin_squared >= 1.0 {
+        return None;
+    }
+    // 1 - cos, written so it doesn't round to zero for small, far away spheres
+    let one_minus_cos = sin_squared / (1.0 + (1.0 - sin_squared).sqrt());
+    Some(2.0 * PI * one_minus_cos)
+}
+
 impl Hittable for Sphere {
-    fn hit(&self, r: &crate::ray::Ray, ray_t: Range<f64>, rec: &mut HitRecord) -> bool {
+    fn hit(&self, r: &Ray, ray_t: Range<f64>, rec: &mut HitRecord) -> bool {
         //ray sphere interesctions
         let oc = self.center - r.origin;
         let a = &r.direction.length_squared();
@@ -56,10 +82,53 @@ impl Hittable for Sphere {
         rec.p = r.at(rec.t); // hit point
         let outward_normal = (rec.p - self.center) / self.radius; //normals
         rec.set_face_normal(r, &outward_normal); //more specific normals
-        rec.set_material(Rc::clone(&self.mat));
+        (rec.u, rec.v) = sphere_uv(&outward_normal);
+        rec.set_material(Arc::clone(&self.mat));
 
         return true;
     }
+    fn is_light(&self) -> bool {
+        self.mat.is_emissive()
+    }
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        if !self.hit(
+            &Ray::new(*origin, *direction),
+            0.001..f64::INFINITY,
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+        match cone_size(self.radius, (self.center - *origin).length_squared()) {
+            Some(solid_angle) => 1.0 / solid_angle,
+            None => 1.0 / (4.0 * PI), // inside the sphere, every direction hits it
+        }
+    }
+    fn random_direction(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let to_center = self.center - *origin;
+        let Some(solid_angle) = cone_size(self.radius, to_center.length_squared()) else {
+            return Vec3::random_normalized(sampler);
+        };
+        // a uniform direction inside the cone of directions that hit the sphere
+        let (u, v) = sampler.next_2d();
+        let z = 1.0 - u * solid_angle / (2.0 * PI);
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        let w = to_center.normalized();
+        let (a, b) = w.orthonormal_basis();
+        a * (r * phi.cos()) + b * (r * phi.sin()) + w * z
+    }
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::from(self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+    fn describe(&self, materials: &mut MaterialTable) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Sphere {
+            center: self.center,
+            radius: self.radius,
+            material: materials.name(&self.mat)?,
+        })
+    }
     fn as_string(&self) -> String {
         format!(
             "[ Sphere ] Radius: {}, Position: ({}x, {}z, {}z), material: {:?}",
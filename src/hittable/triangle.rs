@@ -0,0 +1,198 @@
+//! A [`Hittable`] object, Triangle. Contains the [`Hittable`] trait implementation for Triangle.
+//! Triangles are intersected with the Möller–Trumbore algorithm, and can optionally have a normal and UV coordinate per vertex, which are blended across the face.
+
+use crate::{
+    hittable::{aabb::Aabb, HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    sampler::Sampler,
+    scene::{MaterialTable, ObjectDescription},
+    utils::RangeExtensions,
+    vec3::*,
+};
+use std::{ops::Range, sync::Arc};
+
+#[derive(Clone)]
+/// A `Triangle` is a struct that represents a single triangle in 3D space. It has three vertices, optional per-vertex normals and UVs, and a pointer to a material.
+/// The front face of the triangle is the side the vertices appear counter-clockwise from.
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    mat: Arc<dyn Material>,
+}
+
+impl Triangle {
+    /// Creates a new flat shaded `Triangle` with the given vertices and material.
+    pub fn new(a: Point3, b: Point3, c: Point3, mat: Arc<dyn Material>) -> Self {
+        Triangle {
+            vertices: [a, b, c],
+            normals: None,
+            uvs: None,
+            mat,
+        }
+    }
+    /// Sets a normal for each vertex, which are blended across the face for smooth shading.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals.map(Vec3::normalized));
+        self
+    }
+    /// Sets a UV coordinate for each vertex, used to look up textures.
+    /// Without them, the UVs are the barycentric coordinates of the hit.
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+/// Intersects a ray with the triangle `v`, using the Möller–Trumbore algorithm.
+/// Returns the distance along the ray, and the barycentric weights of the second and third vertex.
+pub(crate) fn intersect(v: &[Point3; 3], r: &Ray, ray_t: &Range<f64>) -> Option<(f64, f64, f64)> {
+    let edge1 = v[1] - v[0];
+    let edge2 = v[2] - v[0];
+    let pvec = cross(&r.direction, &edge2);
+    let det = dot(&edge1, &pvec);
+    if det.abs() < 1e-12 {
+        return None; // the ray is parallel to the triangle
+    }
+
+    let inv_det = 1.0 / det;
+    let tvec = r.origin - v[0];
+    let b1 = dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = cross(&tvec, &edge1);
+    let b2 = dot(&r.direction, &qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = dot(&edge2, &qvec) * inv_det;
+    if !ray_t.surrounds(t) {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+/// Fills in a hit record for a hit on a triangle, blending the per-vertex normals and UVs if there are any.
+pub(crate) fn set_hit_record(
+    rec: &mut HitRecord,
+    r: &Ray,
+    (t, b1, b2): (f64, f64, f64),
+    v: &[Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    mat: &Arc<dyn Material>,
+) {
+    let b0 = 1.0 - b1 - b2;
+    rec.t = t;
+    rec.p = r.at(t);
+
+    let outward_normal = cross(&(v[1] - v[0]), &(v[2] - v[0])).normalized();
+    rec.set_face_normal(r, &outward_normal);
+    if let Some(n) = normals {
+        // shading normal, flipped to the same side as the geometric normal
+        let shading = (b0 * n[0] + b1 * n[1] + b2 * n[2]).normalized();
+        rec.normal = if rec.front_face { shading } else { -shading };
+    }
+
+    (rec.u, rec.v) = match uvs {
+        Some(uv) => (
+            b0 * uv[0].0 + b1 * uv[1].0 + b2 * uv[2].0,
+            b0 * uv[0].1 + b1 * uv[1].1 + b2 * uv[2].1,
+        ),
+        None => (b1, b2),
+    };
+    rec.set_material(Arc::clone(mat));
+}
+
+/// Returns the bounding box of a triangle, padded slightly so flat triangles still have some volume.
+pub(crate) fn bounding_box(v: &[Point3; 3]) -> Aabb {
+    let bbox = Aabb::surrounding(&Aabb::new(v[0], v[1]), &Aabb::new(v[2], v[2]));
+    Aabb::new(bbox.min - 1e-6, bbox.max + 1e-6)
+}
+
+/// Returns the probability density of `random_direction` picking `direction` from `origin`, per unit of solid angle.
+pub(crate) fn pdf_value(v: &[Point3; 3], origin: &Point3, direction: &Vec3) -> f64 {
+    let r = Ray::new(*origin, *direction);
+    let Some((t, _, _)) = intersect(v, &r, &(0.001..f64::INFINITY)) else {
+        return 0.0;
+    };
+    // turns the density per unit of area into one per unit of solid angle
+    let area_normal = cross(&(v[1] - v[0]), &(v[2] - v[0]));
+    let area = area_normal.length() / 2.0;
+    let cos = dot(&area_normal.normalized(), &direction.normalized()).abs();
+    let dist_squared = t * t * direction.length_squared();
+    dist_squared / (cos * area)
+}
+
+/// Returns a direction from `origin` towards a uniform random point on a triangle.
+pub(crate) fn random_direction(
+    v: &[Point3; 3],
+    origin: &Point3,
+    sampler: &mut dyn Sampler,
+) -> Vec3 {
+    let (u, w) = sampler.next_2d();
+    let su = u.sqrt();
+    let [a, b, c] = *v;
+    a * (1.0 - su) + b * (su * (1.0 - w)) + c * (su * w) - *origin
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Range<f64>, rec: &mut HitRecord) -> bool {
+        match intersect(&self.vertices, r, &ray_t) {
+            Some(hit) => {
+                set_hit_record(
+                    rec,
+                    r,
+                    hit,
+                    &self.vertices,
+                    self.normals,
+                    self.uvs,
+                    &self.mat,
+                );
+                true
+            }
+            None => false,
+        }
+    }
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(bounding_box(&self.vertices))
+    }
+    fn is_light(&self) -> bool {
+        self.mat.is_emissive()
+    }
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        pdf_value(&self.vertices, origin, direction)
+    }
+    fn random_direction(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        random_direction(&self.vertices, origin, sampler)
+    }
+    fn describe(&self, materials: &mut MaterialTable) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Triangle {
+            vertices: self.vertices,
+            normals: self.normals,
+            uvs: self.uvs,
+            material: materials.name(&self.mat)?,
+        })
+    }
+    fn as_string(&self) -> String {
+        let [a, b, c] = self.vertices;
+        format!(
+            "[ Triangle ] Vertices: ({}, {}, {}), ({}, {}, {}), ({}, {}, {}), material: {:?}",
+            a.x, a.y, a.z, b.x, b.y, b.z, c.x, c.y, c.z, self.mat
+        )
+    }
+    fn as_info_vec(&self) -> Vec<String> {
+        let center = (self.vertices[0] + self.vertices[1] + self.vertices[2]) / 3.0;
+        vec![
+            "Triangle".to_string(),
+            "-".to_string(),
+            center.x.to_string(),
+            center.y.to_string(),
+            center.z.to_string(),
+            format!("{:?}", self.mat),
+        ]
+    }
+}
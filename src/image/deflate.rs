@@ -0,0 +1,223 @@
+//! A small, homemade zlib encoder, used by the PNG writer.
+//! Data is compressed with LZ77 ( hash chains over a 32KB window ) and the fixed Huffman codes from the DEFLATE spec, which is a lot simpler than building custom codes, and still compresses renders well.
+
+/// The size of the LZ77 window, the furthest back a match can point.
+const WINDOW_SIZE: usize = 32 * 1024;
+/// The shortest match worth encoding.
+const MIN_MATCH: usize = 3;
+/// The longest match DEFLATE can encode.
+const MAX_MATCH: usize = 258;
+/// How many earlier positions are checked for a match, higher values compress better but slower.
+const MAX_CHAIN: usize = 128;
+const HASH_BITS: usize = 15;
+
+/// The base lengths of the length codes 257-285, and how many extra bits follow each.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// The base distances of the distance codes 0-29, and how many extra bits follow each.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Compresses `data` into a zlib stream ( a DEFLATE stream, with a header and checksum ).
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::default();
+    out.bytes.extend_from_slice(&[0x78, 0x01]); // 32K window, no preset dictionary
+
+    out.write_bits(1, 1); // final block
+    out.write_bits(1, 2); // fixed huffman codes
+    compress_block(data, &mut out);
+    write_literal(256, &mut out); // end of block
+    out.flush();
+
+    out.bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    out.bytes
+}
+
+/// Finds matches with hash chains, and writes them as literals and length/distance pairs.
+fn compress_block(data: &[u8], out: &mut BitWriter) {
+    let mut chains = HashChains::new(data);
+    let mut i = 0;
+    while i < data.len() {
+        let (length, distance) = chains.longest_match(i);
+        if length >= MIN_MATCH {
+            write_match(length, distance, out);
+            for j in i..i + length {
+                chains.insert(j);
+            }
+            i += length;
+        } else {
+            write_literal(data[i] as u16, out);
+            chains.insert(i);
+            i += 1;
+        }
+    }
+}
+
+/// Remembers earlier positions by the hash of their next 3 bytes, so matches can be found quickly.
+struct HashChains<'a> {
+    data: &'a [u8],
+    /// The most recent position with each hash
+    head: Vec<usize>,
+    /// The previous position with the same hash as each position in the window
+    prev: Vec<usize>,
+}
+
+impl<'a> HashChains<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        HashChains {
+            data,
+            head: vec![usize::MAX; 1 << HASH_BITS],
+            prev: vec![usize::MAX; WINDOW_SIZE],
+        }
+    }
+
+    fn hash(&self, i: usize) -> usize {
+        let d = self.data;
+        let v = (d[i] as usize) << 16 | (d[i + 1] as usize) << 8 | d[i + 2] as usize;
+        (v.wrapping_mul(2654435761) >> 8) & ((1 << HASH_BITS) - 1)
+    }
+
+    fn insert(&mut self, i: usize) {
+        if i + MIN_MATCH <= self.data.len() {
+            let h = self.hash(i);
+            self.prev[i % WINDOW_SIZE] = self.head[h];
+            self.head[h] = i;
+        }
+    }
+
+    /// Returns the length and distance of the longest earlier match for the bytes at `i`.
+    fn longest_match(&self, i: usize) -> (usize, usize) {
+        if i + MIN_MATCH > self.data.len() {
+            return (0, 0);
+        }
+        let max_length = MAX_MATCH.min(self.data.len() - i);
+        let mut best = (0, 0);
+        let mut candidate = self.head[self.hash(i)];
+
+        for _ in 0..MAX_CHAIN {
+            // stop at the end of the chain, or once it leaves the window
+            if candidate >= i || i - candidate >= WINDOW_SIZE {
+                break;
+            }
+            let length = self.data[candidate..]
+                .iter()
+                .zip(&self.data[i..i + max_length])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best.0 {
+                best = (length, i - candidate);
+                if length == max_length {
+                    break;
+                }
+            }
+            candidate = self.prev[candidate % WINDOW_SIZE];
+        }
+        best
+    }
+}
+
+/// Writes a literal byte or the end of block marker, using the fixed literal/length code.
+fn write_literal(symbol: u16, out: &mut BitWriter) {
+    let (code, bits) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    };
+    out.write_huffman(code as u32, bits);
+}
+
+fn write_match(length: usize, distance: usize, out: &mut BitWriter) {
+    let code = LENGTH_BASE
+        .iter()
+        .rposition(|&base| base as usize <= length)
+        .unwrap_or(0);
+    write_literal(257 + code as u16, out);
+    out.write_bits(
+        (length - LENGTH_BASE[code] as usize) as u32,
+        LENGTH_EXTRA[code],
+    );
+
+    let code = DIST_BASE
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .unwrap_or(0);
+    out.write_huffman(code as u32, 5);
+    out.write_bits(
+        (distance - DIST_BASE[code] as usize) as u32,
+        DIST_EXTRA[code],
+    );
+}
+
+/// Packs bits into bytes, least significant bit first, as DEFLATE expects.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u8,
+}
+
+impl BitWriter {
+    fn write_bits(&mut self, value: u32, bits: u8) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+    /// Huffman codes are packed most significant bit first, so they're reversed before writing.
+    fn write_huffman(&mut self, code: u32, bits: u8) {
+        self.write_bits(code.reverse_bits() >> (32 - bits), bits);
+    }
+    fn flush(&mut self) {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer = 0;
+            self.count = 0;
+        }
+    }
+}
+
+/// The Adler-32 checksum, used at the end of zlib streams.
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before `b` could overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+/// The CRC-32 checksum, used by every PNG chunk.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,200 @@
+//! A minimal OpenEXR writer, for scanline images with 32 bit float R, G and B channels, and a layer for each AOV.
+
+use std::io::{self, Write};
+
+use super::deflate::zlib_compress;
+use crate::film::Film;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+/// How the pixel data of an OpenEXR image is compressed.
+pub enum ExrCompression {
+    /// No compression, one scanline per block.
+    None,
+    /// Lossless zlib compression, 16 scanlines per block.
+    #[default]
+    Zip,
+}
+
+impl ExrCompression {
+    /// The id of the compression method in the file header.
+    fn id(self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zip => 3,
+        }
+    }
+    /// The number of scanlines stored together in each block.
+    fn lines_per_block(self) -> u32 {
+        match self {
+            ExrCompression::None => 1,
+            ExrCompression::Zip => 16,
+        }
+    }
+}
+
+/// A channel of the image: its name, the film it comes from, and which component of the colors it holds.
+struct Channel {
+    name: String,
+    film: usize,
+    component: usize,
+}
+
+/// Writes a film as an OpenEXR image, with full 32 bit float R, G and B channels.
+/// Each AOV layer of the film is written too, with channels named after it, like `albedo.R` or `depth.Z`.
+pub fn write_exr<W: Write>(w: &mut W, film: &Film, compression: ExrCompression) -> io::Result<()> {
+    let (width, height) = (film.width(), film.height());
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "OpenEXR images can't be empty",
+        ));
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]); // magic number
+    header.extend_from_slice(&2u32.to_le_bytes()); // version 2, single part scanline image
+
+    // the main image is film 0, and each layer is the film after it
+    let layers: Vec<Film> = film.aovs().iter().filter_map(|aov| film.aov(aov)).collect();
+    let films: Vec<&Film> = std::iter::once(film).chain(&layers).collect();
+    let mut channels: Vec<Channel> = ["R", "G", "B"]
+        .iter()
+        .enumerate()
+        .map(|(component, name)| Channel {
+            name: name.to_string(),
+            film: 0,
+            component,
+        })
+        .collect();
+    for (layer, aov) in film.aovs().iter().enumerate() {
+        for (component, name) in aov.channels().iter().enumerate() {
+            channels.push(Channel {
+                name: format!("{}.{}", aov.name(), name),
+                film: layer + 1,
+                component,
+            });
+        }
+    }
+    // channels are stored in alphabetical order, each as a 32 bit float with no subsampling
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut channel_list = Vec::new();
+    for channel in &channels {
+        channel_list.extend_from_slice(channel.name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&2i32.to_le_bytes()); // FLOAT
+        channel_list.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+    let mut window = Vec::new();
+    for value in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+
+    write_attribute(&mut header, "channels", "chlist", &channel_list);
+    write_attribute(
+        &mut header,
+        "compression",
+        "compression",
+        &[compression.id()],
+    );
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]); // increasing y
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    let blocks: Vec<Vec<u8>> = (0..height)
+        .step_by(compression.lines_per_block() as usize)
+        .map(|y| {
+            let lines = compression.lines_per_block().min(height - y);
+            write_block(&films, &channels, y, lines, compression)
+        })
+        .collect();
+
+    // the offset table points to the start of each block, from the start of the file
+    let mut offset = (header.len() + blocks.len() * 8) as u64;
+    for block in &blocks {
+        header.extend_from_slice(&offset.to_le_bytes());
+        offset += block.len() as u64;
+    }
+    w.write_all(&header)?;
+    for block in &blocks {
+        w.write_all(block)?;
+    }
+    Ok(())
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Packs `lines` scanlines starting at `y` into a block, compressing them if that makes them smaller.
+fn write_block(
+    films: &[&Film],
+    channels: &[Channel],
+    y: u32,
+    lines: u32,
+    compression: ExrCompression,
+) -> Vec<u8> {
+    // each scanline holds every value of the first channel, then every value of the next one, and so on
+    let width = films[0].width();
+    let mut data = Vec::with_capacity(width as usize * lines as usize * channels.len() * 4);
+    for line in y..y + lines {
+        for channel in channels {
+            let film = &films[channel.film];
+            for x in 0..width {
+                let value = film.pixel(x, line)[channel.component] as f32;
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+
+    if compression == ExrCompression::Zip {
+        let compressed = zlib_compress(&zip_predictor(&data));
+        // readers treat a block the size of the raw data as uncompressed
+        if compressed.len() < data.len() {
+            data = compressed;
+        }
+    }
+
+    let mut block = Vec::with_capacity(data.len() + 8);
+    block.extend_from_slice(&(y as i32).to_le_bytes());
+    block.extend_from_slice(&(data.len() as i32).to_le_bytes());
+    block.extend_from_slice(&data);
+    block
+}
+
+/// Prepares data for ZIP compression the way OpenEXR expects: the even and odd bytes are split into two halves, and each byte is replaced by its difference from the one before.
+fn zip_predictor(data: &[u8]) -> Vec<u8> {
+    let mut split: Vec<u8> = data.iter().step_by(2).copied().collect();
+    split.extend(data.iter().skip(1).step_by(2));
+
+    // the first byte is left as it is
+    let mut previous = split.first().copied().unwrap_or(0);
+    for byte in split.iter_mut().skip(1) {
+        let current = *byte;
+        *byte = current.wrapping_sub(previous).wrapping_add(128);
+        previous = current;
+    }
+    split
+}
@@ -0,0 +1,60 @@
+//! Writers for the Radiance RGBE ( `.hdr` ) and PFM float image formats.
+
+use std::io::{self, Write};
+
+use crate::{color::Color, film::Film};
+
+/// Writes a film as a Radiance RGBE ( `.hdr` ) image.
+/// Each pixel is stored as 3 mantissas sharing one exponent, so it only takes 4 bytes but still covers a huge range of brightness. Scanlines are written flat, without run length encoding.
+pub fn write_hdr<W: Write>(w: &mut W, film: &Film) -> io::Result<()> {
+    write!(
+        w,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        film.height(),
+        film.width()
+    )?;
+    let pixels = film.pixels();
+    let mut bytes = Vec::with_capacity(pixels.len() * 4);
+    for color in pixels {
+        bytes.extend_from_slice(&to_rgbe(color));
+    }
+    w.write_all(&bytes)
+}
+
+/// Packs a color into RGBE, negative values are clamped to 0.
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let (r, g, b) = (color.x.max(0.), color.y.max(0.), color.z.max(0.));
+    let max = r.max(g).max(b);
+    if max < 1e-32 || !max.is_finite() {
+        return [0, 0, 0, 0];
+    }
+    // the exponent is picked so the brightest channel's mantissa lands in 128..256
+    let mut exponent = max.log2().floor() as i32 + 1;
+    if max >= 2f64.powi(exponent) {
+        exponent += 1; // log2 can round down right below a power of 2
+    }
+    let exponent = exponent.clamp(-128, 127);
+    let scale = 256.0 / 2f64.powi(exponent);
+    [
+        (r * scale).min(255.) as u8,
+        (g * scale).min(255.) as u8,
+        (b * scale).min(255.) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+/// Writes a film as a color PFM image, 32 bit little endian floats, with rows from bottom to top as the format expects.
+pub fn write_pfm<W: Write>(w: &mut W, film: &Film) -> io::Result<()> {
+    // a negative scale marks the data as little endian
+    write!(w, "PF\n{} {}\n-1.0\n", film.width(), film.height())?;
+    let pixels = film.pixels();
+    let mut bytes = Vec::with_capacity(pixels.len() * 12);
+    for row in pixels.chunks_exact(film.width().max(1) as usize).rev() {
+        for color in row {
+            for channel in [color.x, color.y, color.z] {
+                bytes.extend_from_slice(&(channel as f32).to_le_bytes());
+            }
+        }
+    }
+    w.write_all(&bytes)
+}
@@ -0,0 +1,355 @@
+//! This module writes rendered images to files, so nobody has to write image headers by hand.
+//! The writers take the `[R, G, B, R, G, B, ...]` buffers returned by `Camera::render_to_bytes`, and can write:
+//! - PNG, compressed with a homemade zlib encoder.
+//! - Binary ( P6 ) PPM.
+//! - Plain text ( P3 ) PPM.
+//!
+//! [`Film`]s can also be saved to HDR formats, which keep the full linear radiance of the render for compositing and grading:
+//! - OpenEXR, uncompressed or ZIP compressed, with 32 bit float channels.
+//! - Radiance RGBE ( `.hdr` ).
+//! - PFM.
+//!
+//! # Example
+//! ```no_run
+//! use rtwlib::{camera::Camera, hittable::HittableList, image};
+//!
+//! let mut cam = Camera::new();
+//! let buffer = cam.render_to_bytes(HittableList::new(), |_| {});
+//! image::save_image("render.png", cam.image_width, cam.get_height(), &buffer).expect("failed to save");
+//! ```
+mod deflate;
+mod exr;
+mod hdr;
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use self::deflate::{crc32, zlib_compress};
+pub use self::{
+    exr::{write_exr, ExrCompression},
+    hdr::{write_hdr, write_pfm},
+};
+use crate::{film::Film, tonemap::OutputTransform};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The image formats that can be written.
+pub enum ImageFormat {
+    /// A compressed PNG image.
+    Png,
+    /// A binary ( P6 ) PPM image.
+    Ppm,
+    /// A plain text ( P3 ) PPM image, bigger but human readable.
+    PpmAscii,
+    /// An OpenEXR image with float channels, HDR.
+    Exr(ExrCompression),
+    /// A Radiance RGBE image, HDR.
+    Hdr,
+    /// A PFM image with float channels, HDR.
+    Pfm,
+}
+
+impl ImageFormat {
+    /// Picks a format from the extension of a file name: `.png`, `.ppm`, `.exr`, `.hdr` or `.pfm`.
+    /// `.ppm` files are written in binary, since it's what everything expects, and `.exr` files are ZIP compressed.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            "exr" => Some(ImageFormat::Exr(ExrCompression::Zip)),
+            "hdr" => Some(ImageFormat::Hdr),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+    /// Returns true for formats that store float colors, which have to be written from a [`Film`].
+    pub fn is_hdr(self) -> bool {
+        matches!(
+            self,
+            ImageFormat::Exr(_) | ImageFormat::Hdr | ImageFormat::Pfm
+        )
+    }
+    /// Writes an RGB image in this format.
+    /// Returns an error for HDR formats, since 8 bit colors have already lost their range, use [`ImageFormat::write_film`] for those.
+    pub fn write<W: Write>(self, w: &mut W, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+        match self {
+            ImageFormat::Png => write_png(w, width, height, rgb),
+            ImageFormat::Ppm => write_ppm(w, width, height, rgb),
+            ImageFormat::PpmAscii => write_ppm_ascii(w, width, height, rgb),
+            ImageFormat::Exr(_) | ImageFormat::Hdr | ImageFormat::Pfm => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "HDR formats have to be written from a film",
+            )),
+        }
+    }
+    /// Writes a film in this format.
+    /// HDR formats get the linear colors as they are, 8 bit formats are encoded with `output` first.
+    pub fn write_film<W: Write>(
+        self,
+        w: &mut W,
+        film: &Film,
+        output: &OutputTransform,
+    ) -> io::Result<()> {
+        match self {
+            ImageFormat::Exr(compression) => write_exr(w, film, compression),
+            ImageFormat::Hdr => write_hdr(w, film),
+            ImageFormat::Pfm => write_pfm(w, film),
+            _ => self.write(w, film.width(), film.height(), &film.to_rgb_bytes(output)),
+        }
+    }
+}
+
+/// Saves an RGB image to a file, picking the format from its extension with [`ImageFormat::from_path`].
+pub fn save_image<P: AsRef<Path>>(path: P, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    let format = format_from_path(&path)?;
+    let mut file = BufWriter::new(File::create(path)?);
+    format.write(&mut file, width, height, rgb)?;
+    file.flush()
+}
+
+/// Saves a film to a file, picking the format from its extension with [`ImageFormat::from_path`].
+/// HDR formats keep the linear colors, 8 bit formats are encoded with `output` first.
+pub fn save_film<P: AsRef<Path>>(path: P, film: &Film, output: &OutputTransform) -> io::Result<()> {
+    let format = format_from_path(&path)?;
+    let mut file = BufWriter::new(File::create(path)?);
+    format.write_film(&mut file, film, output)?;
+    file.flush()
+}
+
+fn format_from_path<P: AsRef<Path>>(path: P) -> io::Result<ImageFormat> {
+    ImageFormat::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "unsupported image extension, expected .png, .ppm, .exr, .hdr or .pfm",
+        )
+    })
+}
+
+/// Checks that the image isn't empty, and that the buffer holds exactly one RGB triple per pixel.
+fn check_size(width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "images need at least one pixel",
+        ));
+    }
+    if rgb.len() != width as usize * height as usize * 3 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "buffer size doesn't match the image size",
+        ));
+    }
+    Ok(())
+}
+
+/// Writes an RGB image as a binary ( P6 ) PPM.
+pub fn write_ppm<W: Write>(w: &mut W, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    check_size(width, height, rgb)?;
+    write!(w, "P6\n{} {}\n255\n", width, height)?;
+    w.write_all(rgb)
+}
+
+/// Writes an RGB image as a plain text ( P3 ) PPM, with one pixel per line.
+pub fn write_ppm_ascii<W: Write>(w: &mut W, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    check_size(width, height, rgb)?;
+    write!(w, "P3\n{} {}\n255\n", width, height)?;
+    for pixel in rgb.chunks_exact(3) {
+        writeln!(w, "{} {} {}", pixel[0], pixel[1], pixel[2])?;
+    }
+    Ok(())
+}
+
+/// Writes an RGB image as an 8 bit PNG.
+/// # Example
+/// The PNG holds exactly the same pixels as a PPM of the same image:
+/// ```
+/// use rtwlib::image::{write_png, write_ppm};
+///
+/// // a gradient with some repeats, so every row filter and plenty of matches get used
+/// let (width, height) = (67, 45);
+/// let rgb: Vec<u8> = (0..width * height * 3)
+///     .map(|i| ((i % 7) * 31 + i / 200 + (i / 3 % width) * ((i / 3 / width) % 3)) as u8)
+///     .collect();
+///
+/// let (mut png, mut ppm) = (Vec::new(), Vec::new());
+/// write_png(&mut png, width, height, &rgb).unwrap();
+/// write_ppm(&mut ppm, width, height, &rgb).unwrap();
+/// let header = format!("P6\n{} {}\n255\n", width, height);
+/// assert_eq!(decode_png(&png), &ppm[header.len()..]);
+/// # // a minimal PNG decoder, only supporting what `write_png` writes ( fixed huffman codes )
+/// # fn decode_png(png: &[u8]) -> Vec<u8> {
+/// #     assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+/// #     let (mut pos, mut width, mut height, mut zlib) = (8, 0, 0, Vec::new());
+/// #     while pos < png.len() {
+/// #         let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+/// #         let (chunk, data) = (&png[pos + 4..pos + 8 + len], &png[pos + 8..pos + 8 + len]);
+/// #         let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+/// #         assert_eq!(crc32(chunk), crc, "bad chunk crc");
+/// #         match &chunk[..4] {
+/// #             b"IHDR" => {
+/// #                 width = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+/// #                 height = u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize;
+/// #             }
+/// #             b"IDAT" => zlib.extend_from_slice(data),
+/// #             _ => {}
+/// #         }
+/// #         pos += len + 12;
+/// #     }
+/// #     let filtered = inflate(&zlib);
+/// #     let stride = width * 3;
+/// #     let mut out: Vec<u8> = Vec::new();
+/// #     for y in 0..height {
+/// #         let row = &filtered[y * (stride + 1)..(y + 1) * (stride + 1)];
+/// #         for x in 0..stride {
+/// #             let left = if x >= 3 { out[y * stride + x - 3] as i16 } else { 0 };
+/// #             let above = if y > 0 { out[(y - 1) * stride + x] as i16 } else { 0 };
+/// #             let upper_left = if x >= 3 && y > 0 { out[(y - 1) * stride + x - 3] as i16 } else { 0 };
+/// #             let predicted = match row[0] {
+/// #                 0 => 0,
+/// #                 1 => left,
+/// #                 2 => above,
+/// #                 3 => (left + above) / 2,
+/// #                 4 => {
+/// #                     let p = left + above - upper_left;
+/// #                     let (pa, pb, pc) = ((p - left).abs(), (p - above).abs(), (p - upper_left).abs());
+/// #                     if pa <= pb && pa <= pc { left } else if pb <= pc { above } else { upper_left }
+/// #                 }
+/// #                 filter => panic!("bad filter {}", filter),
+/// #             };
+/// #             out.push(row[x + 1].wrapping_add(predicted as u8));
+/// #         }
+/// #     }
+/// #     out
+/// # }
+/// # fn inflate(zlib: &[u8]) -> Vec<u8> {
+/// #     const LENGTH_BASE: [usize; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+/// #     const DIST_BASE: [usize; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+/// #     const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+/// #     const DIST_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// #     let mut bit = 16; // after the zlib header
+/// #     let mut read = |n: u32| (0..n).fold(0, |v, i| { let b = (zlib[bit / 8] >> (bit % 8)) & 1; bit += 1; v | (b as usize) << i });
+/// #     assert_eq!(read(3), 0b011, "expected one final block with fixed codes");
+/// #     let mut out: Vec<u8> = Vec::new();
+/// #     loop {
+/// #         // fixed huffman codes are read most significant bit first
+/// #         let mut code = (0..7).fold(0, |c, _| c << 1 | read(1));
+/// #         let symbol = if code <= 0b0010111 { 256 + code } else {
+/// #             code = code << 1 | read(1);
+/// #             match code { 0x30..=0xbf => code - 0x30, 0xc0..=0xc7 => 280 + code - 0xc0, _ => 144 + (code << 1 | read(1)) - 0x190 }
+/// #         };
+/// #         match symbol {
+/// #             0..=255 => out.push(symbol as u8),
+/// #             256 => break,
+/// #             _ => {
+/// #                 let i = symbol - 257;
+/// #                 let length = LENGTH_BASE[i] + read(LENGTH_EXTRA[i]);
+/// #                 let d = (0..5).fold(0, |c, _| c << 1 | read(1));
+/// #                 let distance = DIST_BASE[d] + read(DIST_EXTRA[d]);
+/// #                 for _ in 0..length { out.push(out[out.len() - distance]); }
+/// #             }
+/// #         }
+/// #     }
+/// #     let (mut a, mut b) = (1u32, 0u32);
+/// #     for &byte in &out { a = (a + byte as u32) % 65521; b = (b + a) % 65521; }
+/// #     assert_eq!(zlib[zlib.len() - 4..], (b << 16 | a).to_be_bytes(), "bad adler32");
+/// #     out
+/// # }
+/// # fn crc32(data: &[u8]) -> u32 {
+/// #     !data.iter().fold(!0u32, |crc, &byte| (0..8).fold(crc ^ byte as u32, |c, _| if c & 1 == 1 { 0xedb88320 ^ (c >> 1) } else { c >> 1 }))
+/// # }
+/// ```
+pub fn write_png<W: Write>(w: &mut W, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    check_size(width, height, rgb)?;
+    w.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8 bit RGB, default compression and filtering, no interlacing
+    write_chunk(w, b"IHDR", &header)?;
+
+    write_chunk(
+        w,
+        b"IDAT",
+        &zlib_compress(&filter_rows(width as usize * 3, rgb)),
+    )?;
+    write_chunk(w, b"IEND", &[])
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+
+    let mut crc_data = Vec::with_capacity(data.len() + 4);
+    crc_data.extend_from_slice(kind);
+    crc_data.extend_from_slice(data);
+    w.write_all(&crc32(&crc_data).to_be_bytes())
+}
+
+/// Applies a PNG filter to each row, which makes the data much easier to compress.
+/// Every filter is tried on each row, and the one with the smallest output ( by sum of absolute values ) is kept.
+fn filter_rows(stride: usize, rgb: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(rgb.len() + rgb.len() / stride.max(1));
+    let empty = vec![0u8; stride];
+    let mut candidate = vec![0u8; stride];
+    let mut best = vec![0u8; stride];
+
+    for (y, row) in rgb.chunks_exact(stride).enumerate() {
+        let above = if y == 0 {
+            &empty[..]
+        } else {
+            &rgb[(y - 1) * stride..y * stride]
+        };
+        let mut best_filter = 0;
+        let mut best_score = u64::MAX;
+
+        for filter in 0..5u8 {
+            for x in 0..stride {
+                let left = if x >= 3 { row[x - 3] } else { 0 };
+                let upper_left = if x >= 3 { above[x - 3] } else { 0 };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => above[x],
+                    3 => ((left as u16 + above[x] as u16) / 2) as u8,
+                    _ => paeth(left, above[x], upper_left),
+                };
+                candidate[x] = row[x].wrapping_sub(predicted);
+            }
+            // treat bytes as signed, so small negative differences count as small
+            let score = candidate
+                .iter()
+                .map(|&b| (b as i8).unsigned_abs() as u64)
+                .sum();
+            if score < best_score {
+                best_score = score;
+                best_filter = filter;
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+        out.push(best_filter);
+        out.extend_from_slice(&best);
+    }
+    out
+}
+
+/// The Paeth predictor, picks whichever neighbour is closest to `left + above - upper_left`.
+fn paeth(left: u8, above: u8, upper_left: u8) -> u8 {
+    let p = left as i16 + above as i16 - upper_left as i16;
+    let (pa, pb, pc) = (
+        (p - left as i16).abs(),
+        (p - above as i16).abs(),
+        (p - upper_left as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        left
+    } else if pb <= pc {
+        above
+    } else {
+        upper_left
+    }
+}
@@ -0,0 +1,406 @@
+//! This module contains the [`Integrator`] trait, which decides what color a camera ray brings back, and the premade integrators.
+//! The camera only works out where rays start and where their colors go in the image, the light transport itself is up to the camera's `integrator`, so trying a different algorithm doesn't mean rewriting the render loop.
+//!
+//! The premade integrators are:
+//! - [`PathIntegrator`]: The default, a path tracer that follows rays as they bounce around the scene, with direct light sampling at diffuse hits.
+//! - [`AmbientOcclusion`]: Shades every surface by how much of the space around it is open, ignoring materials and lights. Quick to render, and handy for checking geometry.
+//! - [`DebugIntegrator`]: Shows the normals, depth, albedo, object or material ids, or UVs of the surfaces the camera sees.
+//!
+//! # Example
+//! ```
+//! use rtwlib::{camera::Camera, hittable::HittableList, integrator::AmbientOcclusion};
+//!
+//! let mut cam = Camera::new();
+//! (cam.image_width, cam.image_height, cam.samples) = (32, 18, 4);
+//! cam.integrator = Box::new(AmbientOcclusion::new(0.5));
+//! let image = cam.render_to_bytes(HittableList::new(), |_| {});
+//! ```
+use crate::{
+    aov::AovSample,
+    camera::Camera,
+    color::Color,
+    hittable::{HitRecord, Hittable, HittableList},
+    ray::Ray,
+    sampler::{mix, Sampler},
+    scene::IntegratorDescription,
+    stable_hash::stable_hash,
+    vec3::*,
+};
+use std::sync::Arc;
+
+/// Any object that implements the `Integrator` trait can be used by a camera to work out the color of its rays.
+pub trait Integrator: IntegratorClone + Send + Sync {
+    /// Returns the light arriving at the camera along `r`.
+    /// * `r` - The camera ray.
+    /// * `camera` - The camera doing the render, for settings like `bounces` and the `sky`.
+    /// * `world` - A HittableList of objects, representing the scene.
+    /// * `sampler` - The source of random numbers, already started for this sample.
+    fn radiance(
+        &self,
+        r: Ray,
+        camera: &Camera,
+        world: &HittableList,
+        sampler: &mut dyn Sampler,
+    ) -> Color;
+    /// Like `radiance`, but also fills in `aovs` with what the ray found, for the camera's [`Aov`](crate::aov::Aov) layers.
+    /// The default counts all of the light as direct, and traces the ray again to fill in the first hit.
+    fn radiance_with_aovs(
+        &self,
+        r: Ray,
+        camera: &Camera,
+        world: &HittableList,
+        sampler: &mut dyn Sampler,
+        aovs: &mut AovSample,
+    ) -> Color {
+        let color = self.radiance(r, camera, world, sampler);
+        aovs.direct = color;
+        let mut rec: HitRecord = Default::default();
+        if world.hit(&r, 0.001..f64::INFINITY, &mut rec) {
+            aovs.record_hit(&rec, camera);
+        }
+        color
+    }
+    /// Describes the integrator, so it can be saved in a scene file. Returns `None` if it can't be described.
+    fn describe(&self) -> Option<IntegratorDescription> {
+        None
+    }
+}
+
+/// A trait to allow cloning of an `Integrator` object.
+pub trait IntegratorClone {
+    /// Clones the integrator as a boxed trait object.
+    fn clone_box(&self) -> Box<dyn Integrator>;
+}
+
+impl<T> IntegratorClone for T
+where
+    T: 'static + Integrator + Clone,
+{
+    fn clone_box(&self) -> Box<dyn Integrator> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Integrator> {
+    fn clone(&self) -> Box<dyn Integrator> {
+        self.clone_box()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// A path tracer, the default integrator. Rays bounce around the scene up to the camera's `bounces` times, picking up the light emitted by whatever they hit, and the sky when they escape.
+/// At diffuse hits, a shadow ray is also sent straight at a random light from the world's light list ( next event estimation ), and the two ways of finding a light are blended with multiple importance sampling, so small bright lights don't turn into fireflies.
+pub struct PathIntegrator;
+
+/// The light a path brings back from one of its hits, split up for the direct and indirect AOVs.
+#[derive(Clone, Copy)]
+struct PathLight {
+    /// Light emitted at the hit, or the sky if the ray missed
+    emitted: Color,
+    /// Light from one bounce further, that came straight from a light or the sky
+    direct: Color,
+    /// Everything else
+    indirect: Color,
+}
+
+impl PathLight {
+    fn total(self) -> Color {
+        self.emitted + self.direct + self.indirect
+    }
+}
+
+impl PathIntegrator {
+    /// Traces a ray through the scene, following it for at most `bounces` hits, and returns the light it brings back.
+    pub fn trace(
+        &self,
+        r: Ray,
+        bounces: u32,
+        camera: &Camera,
+        world: &HittableList,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        self.path(r, bounces, camera, world, sampler, None, None)
+            .total()
+    }
+    /// The recursive part of `trace`. `scatter_pdf` is the chance the last hit had of scattering towards `r`, or `None` for camera rays and mirror like bounces, where hitting a light can't have been found by a shadow ray.
+    /// If there are `aovs`, the hit is recorded in them.
+    #[allow(clippy::too_many_arguments)]
+    fn path(
+        &self,
+        r: Ray,
+        bounces: u32,
+        camera: &Camera,
+        world: &HittableList,
+        sampler: &mut dyn Sampler,
+        scatter_pdf: Option<f64>,
+        aovs: Option<&mut AovSample>,
+    ) -> PathLight {
+        let black = Color::from(0.);
+        let mut light = PathLight {
+            emitted: black,
+            direct: black,
+            indirect: black,
+        };
+        if bounces == 0 {
+            return light;
+        }
+
+        let mut rec: HitRecord = Default::default();
+
+        if world.hit(&r, 0.001..f64::INFINITY, &mut rec) {
+            if let Some(aovs) = aovs {
+                aovs.record_hit(&rec, camera);
+            }
+            light.emitted = rec.mat.emitted(&rec);
+            if let Some(pdf) = scatter_pdf {
+                // this light could also have been found by a shadow ray, which already counted its share
+                light.emitted =
+                    light.emitted * power_heuristic(pdf, world.light_pdf(&r.origin, &r.direction));
+            }
+
+            let mut scattered = Ray::new(Vec3::from(0.), Vec3::from(0.));
+            let mut attenuation = Color::from(1.);
+            if !rec
+                .mat
+                .scatter(&r, &rec, &mut attenuation, &mut scattered, sampler)
+            {
+                return light; // black for most materials, shows up around the edge of metals
+            }
+
+            let pdf = rec.mat.pdf(&r, &rec, scattered.direction);
+            // a pdf means the material can be evaluated in any direction, so it can be lit directly.
+            // the light has to be one bounce away, so the last bounce is skipped like it is for scattered rays
+            if pdf > 0. && bounces > 1 {
+                light.direct = sample_light(&r, &rec, world, sampler);
+            }
+            //does bounce/scattter for materials of hit object
+            let next = self.path(
+                scattered,
+                bounces - 1,
+                camera,
+                world,
+                sampler,
+                (pdf > 0.).then_some(pdf),
+                None,
+            );
+            light.direct += attenuation * next.emitted;
+            light.indirect = attenuation * (next.direct + next.indirect);
+            return light;
+        }
+
+        // if the ray hits nothing, calculates a sky color
+        light.emitted = camera.sky.color(r);
+        light
+    }
+}
+
+impl Integrator for PathIntegrator {
+    fn radiance(
+        &self,
+        r: Ray,
+        camera: &Camera,
+        world: &HittableList,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        self.trace(r, camera.bounces, camera, world, sampler)
+    }
+    fn radiance_with_aovs(
+        &self,
+        r: Ray,
+        camera: &Camera,
+        world: &HittableList,
+        sampler: &mut dyn Sampler,
+        aovs: &mut AovSample,
+    ) -> Color {
+        let light = self.path(r, camera.bounces, camera, world, sampler, None, Some(aovs));
+        aovs.direct = light.emitted + light.direct;
+        aovs.indirect = light.indirect;
+        light.total()
+    }
+    fn describe(&self) -> Option<IntegratorDescription> {
+        Some(IntegratorDescription::Path)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Ambient occlusion, which shades each surface the camera sees by how open the space around it is.
+/// One ray per sample is sent from the hit in a random direction, the sample is white if nothing is in the way for `distance`, and black if something is. Rays that miss everything are white.
+pub struct AmbientOcclusion {
+    /// How far away objects can be and still shade a surface. Bigger values give darker, softer shading.
+    pub distance: f64,
+}
+
+impl AmbientOcclusion {
+    /// Creates a new `AmbientOcclusion` integrator, that looks for objects up to `distance` away.
+    pub fn new(distance: f64) -> Self {
+        AmbientOcclusion { distance }
+    }
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        AmbientOcclusion::new(1.0)
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(
+        &self,
+        r: Ray,
+        _camera: &Camera,
+        world: &HittableList,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut rec: HitRecord = Default::default();
+        if !world.hit(&r, 0.001..f64::INFINITY, &mut rec) {
+            return Color::from(1.);
+        }
+
+        // cosine weighted around the normal, like a diffuse bounce
+        let mut direction = rec.normal + Vec3::random_normalized(sampler);
+        if direction.near_zero() {
+            direction = rec.normal
+        }
+        let occlusion = Ray::new(rec.p, direction.normalized());
+        let mut blocker: HitRecord = Default::default();
+        if world.hit(&occlusion, 0.001..self.distance, &mut blocker) {
+            Color::from(0.)
+        } else {
+            Color::from(1.)
+        }
+    }
+    fn describe(&self) -> Option<IntegratorDescription> {
+        Some(IntegratorDescription::AmbientOcclusion {
+            distance: self.distance,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+/// What a [`DebugIntegrator`] shows about the first thing each camera ray hits.
+pub enum DebugMode {
+    /// The shading normal, facing the camera, with each axis mapped from -1..1 to a channel from 0..1
+    #[default]
+    Normal,
+    /// The distance from the camera along its view direction, in scene units ( not clamped, so save it as HDR, or turn the exposure down )
+    Depth,
+    /// The base color of the material, see `Material::albedo`
+    Albedo,
+    /// A random color for each object, see `HitRecord::object_id`
+    ObjectId,
+    /// A random color for each material
+    MaterialId,
+    /// The U and V surface coordinates, in the red and green channels
+    Uv,
+}
+
+impl DebugMode {
+    /// Returns the number the mode is stored as in scene hashes. These can't change, or old checkpoints stop resuming.
+    pub(crate) fn tag(self) -> u8 {
+        match self {
+            DebugMode::Normal => 0,
+            DebugMode::Depth => 1,
+            DebugMode::Albedo => 2,
+            DebugMode::ObjectId => 3,
+            DebugMode::MaterialId => 4,
+            DebugMode::Uv => 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// Shows one property of the surfaces the camera sees, instead of lighting the scene, to debug geometry and materials without swapping every material for a [`Normal`](crate::material::Normal).
+/// Only the first hit of each camera ray is used, and rays that miss everything are black.
+pub struct DebugIntegrator {
+    /// What to show
+    pub mode: DebugMode,
+}
+
+impl DebugIntegrator {
+    /// Creates a new `DebugIntegrator`, that shows `mode`.
+    pub fn new(mode: DebugMode) -> Self {
+        DebugIntegrator { mode }
+    }
+}
+
+impl Integrator for DebugIntegrator {
+    fn radiance(
+        &self,
+        r: Ray,
+        camera: &Camera,
+        world: &HittableList,
+        _sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut rec: HitRecord = Default::default();
+        if !world.hit(&r, 0.001..f64::INFINITY, &mut rec) {
+            return Color::from(0.);
+        }
+
+        match self.mode {
+            DebugMode::Normal => (rec.normal + 1.) * 0.5,
+            DebugMode::Depth => Color::from(camera.depth(rec.p)),
+            DebugMode::Albedo => rec.mat.albedo(&rec),
+            DebugMode::ObjectId => id_color(rec.object_id as u64),
+            DebugMode::MaterialId => id_color(material_id(&rec)),
+            DebugMode::Uv => Color::new(rec.u, rec.v, 0.),
+        }
+    }
+    fn describe(&self) -> Option<IntegratorDescription> {
+        Some(IntegratorDescription::Debug { mode: self.mode })
+    }
+}
+
+/// Turns an id into a random, fairly bright color, so neighbouring ids are easy to tell apart.
+fn id_color(id: u64) -> Color {
+    let bits = mix(id);
+    let channel = |shift: u32| 0.2 + 0.8 * ((bits >> shift) & 0xff) as f64 / 255.;
+    Color::new(channel(0), channel(8), channel(16))
+}
+
+/// Returns an id for the material that was hit, the same for every render.
+/// Materials that can be described are told apart by their description, others by their address.
+fn material_id(rec: &HitRecord) -> u64 {
+    match rec.mat.describe() {
+        Some(description) => stable_hash(&description),
+        None => stable_hash(&(Arc::as_ptr(&rec.mat) as *const () as usize)),
+    }
+}
+
+/// Sends a shadow ray from a hit towards a random light, and returns the light it brings back, weighted against the chance of the material's own scattered ray finding it.
+fn sample_light(
+    r: &Ray,
+    rec: &HitRecord,
+    world: &HittableList,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    let Some(direction) = world.sample_light(&rec.p, sampler) else {
+        return Color::from(0.);
+    };
+    let light_pdf = world.light_pdf(&rec.p, &direction);
+    let f = rec.mat.eval(r, rec, direction);
+    if light_pdf <= 0. || f.near_zero() {
+        return Color::from(0.);
+    }
+
+    let shadow = Ray::new(rec.p, direction);
+    let mut light_rec: HitRecord = Default::default();
+    if !world.hit(&shadow, 0.001..f64::INFINITY, &mut light_rec) {
+        return Color::from(0.); // missed, the sky is only counted by scattered rays
+    }
+    let weight = power_heuristic(light_pdf, rec.mat.pdf(r, rec, direction));
+    f * light_rec.mat.emitted(&light_rec) * (weight / light_pdf)
+}
+
+/// Veach's power heuristic, the share of a sample found with density `pdf` when `other_pdf` could have found it too
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0. {
+        return 0.;
+    }
+    a / (a + b)
+}
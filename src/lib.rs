@@ -12,38 +12,52 @@
 //! # Get started
 //! To render a scene, you need two things: a `Camera` and a scene, in the form of a `HittableList`.
 //! The following code creates a scene and camera, and renders the scene to a Vec<u8> containing the RGB values of the pixels.
-//! ```
+//! ```no_run
 //! use rtwlib::{camera::Camera, hittable::HittableList};
 //!
 //! fn main() {
-//!     let mut world = HittableList {
-//!         objects: Vec::new(),
-//!     };
+//!     let mut world = HittableList::new();
 //!     let mut cam = Camera::new();
 //!
 //!     let image_bytes = cam.render_to_bytes(world, |progress| println!("Progress: {}%", progress));
 //!     // Do something with the bytes
-//! }a
+//! }
 //! ```
 //! This is the bare minimum needed to render a scene, and will result in a sky background, as no objects have been added to the scene.
 //! ## Adding objects to the scene
-//! Any object that implements the `Hittable` trait can be added to the scene, the base crate only provides a `Sphere` object that can be used, but you can create your own objects by implementing the `Hittable` trait for your own objects.
+//! Any object that implements the `Hittable` trait can be added to the scene, the base crate provides `Sphere`, `Plane`, `Triangle` and `Mesh` objects that can be used, but you can create your own objects by implementing the `Hittable` trait for your own objects.
 //! Objects also need to have an associated material, to inform how light bounces should be calculated which can be any object that implements the `Material` trait, this can be
 //! ```
-//! fn main() {
-//!     let mut world = HittableList::new();
+//! use rtwlib::{color::Color, hittable::{sphere::Sphere, HittableList}, material::Lambertian, vec3::Point3};
+//! use std::sync::Arc;
 //!
-//!     let material = Rc::new(Lambertian::new(Color::new(0.3, 0.86, 0.1))); //create a new material with a vaugely green color
-//!     let sphere = Sphere::new(Point3::new(0., 0., -1.), 0.5, material); //create a new sphere at 0, 0, -1 with a radius of 0.5, using the material we just created
+//! let mut world = HittableList::new();
 //!
-//!     world.add(sphere); //add the sphere to the world
+//! let material = Arc::new(Lambertian::new(Color::new(0.3, 0.86, 0.1))); //create a new material with a vaugely green color
+//! let sphere = Sphere::new(Point3::new(0., 0., -1.), 0.5, material); //create a new sphere at 0, 0, -1 with a radius of 0.5, using the material we just created
 //!
-//!     ...
-//! //
+//! world.add(sphere); //add the sphere to the world
+//! ```
+//! ## Multithreading
+//! The premade render functions split the image into tiles and render them on [`camera::Camera::threads`] worker threads, so everything in a scene is shared through `Arc`s and must be `Send + Sync`.
+pub mod aov;
 pub mod camera;
+pub mod checkpoint;
 pub mod color;
+pub mod denoise;
+pub mod film;
 pub mod hittable;
+pub mod image;
+pub mod integrator;
 pub mod material;
+pub mod obj;
+pub mod progressive;
 pub mod ray;
+pub mod sampler;
+pub mod scene;
+mod stable_hash;
+pub mod texture;
+pub mod tile;
+pub mod tonemap;
 pub mod utils;
 pub mod vec3;
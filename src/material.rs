@@ -1,34 +1,66 @@
 //! `materials` is a collection of types that implement the `Material` trait.
 //! materials are associated with objects, and determine how it interacts with light
 //! Every material has a `scatter` function, which takes an input ray and a hit record, and returns a boolean indicating if the ray was scattered, and modifies the input variables to reflect the scattered ray, colors, and other properties.
+//! Materials that scatter in every direction can also implement `eval` and `pdf`, which let the camera light them straight from the scene's lights.
 //! The available materials are:
 //! - [`Lambertian`]: A diffuse material, effectively reflects light in a random direction, with a color determined by the albedo.
-//! - [`Normal`]: A material that colors the object based on the normal vector at the hit point, mostly a joke, just a fancy colored lambertian.
+//! - [`Normal`]: A material that colors the object based on the normal vector at the hit point, mostly a joke, just a fancy colored lambertian. To look at the normals of a whole scene, use a [`DebugIntegrator`](crate::integrator::DebugIntegrator) instead.
 //! - [`Metal`]: A material that reflects light. The reflectance is determined by the fuzziness of the material, with higher
-use std::fmt::Debug;
+//! - [`DiffuseLight`]: A material that emits light, and doesn't scatter anything. Use it to light a scene with geometry instead of the sky.
+use std::{f64::consts::PI, fmt::Debug, sync::Arc};
 
-use rand::Rng;
-
-use crate::{color::Color, hittable::HitRecord, ray::Ray, vec3::*};
+use crate::{
+    color::Color, hittable::HitRecord, ray::Ray, sampler::Sampler, scene::MaterialDescription,
+    texture::Texture, vec3::*,
+};
 
 /// A `Material` is a trait that represents a material that can be applied to an object. This requires the `scatter` function to be implemented, which describes how the material scatters an incoming ray.
-///
-pub trait Material: Debug {
+pub trait Material: Debug + Send + Sync {
     /// Given an incoming ray and a hit record, this function should return a boolean indicating if the ray was scattered, and modify the input variables to reflect the scattered ray, colors, and other properties.
     /// # Arguments
     /// * `r_in` - The incoming ray
     /// * `rec` - A [`HitRecord`] ( stores location, normal, material,  and other information about the hit )
     /// * `attenuation` - The color of incoming light ray, to be modified by the material
     /// * `scattered` - The scattered ray, to be modified by the material
+    /// * `sampler` - The source of random numbers, used for any random choices the material makes
     fn scatter(
         &self,
         _r_in: &Ray,
         _rec: &HitRecord,
         _attenuation: &mut Color,
         _scattered: &mut Ray,
+        _sampler: &mut dyn Sampler,
     ) -> bool {
         false
     }
+    /// Returns the light emitted by the material at the hit point, this is added to the color of the ray on top of any scattered light.
+    /// Most materials don't emit anything, so this defaults to black.
+    fn emitted(&self, _rec: &HitRecord) -> Color {
+        Color::from(0.)
+    }
+    /// Returns the base color of the material at the hit point, ignoring lighting. This is what debug renders show as the albedo.
+    /// Defaults to black, for materials without a color.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::from(0.)
+    }
+    /// Returns true if the material gives off light. Objects with an emissive material are added to the scene's light list, so the camera can aim rays at them.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+    /// Returns how much light arriving from `direction` is scattered back along the incoming ray, including the cosine term. This is what `scatter` would multiply the light from that direction by, divided by the chance of picking it.
+    /// Used to light the hit point directly from a light, instead of waiting for a scattered ray to find one. Defaults to black, for materials that can't be evaluated like this.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> Color {
+        Color::from(0.)
+    }
+    /// Returns the probability density of `scatter` sending the ray in `direction`, per unit of solid angle.
+    /// Mirrors and glass only scatter in one exact direction, which can't be hit on purpose, so these return 0.0 ( the default ) and are never lit directly.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> f64 {
+        0.0
+    }
+    /// Describes the material, so it can be saved in a scene file. Returns `None` if it can't be described.
+    fn describe(&self) -> Option<MaterialDescription> {
+        None
+    }
     /// Returns a string representation of the material, for debugging purposes.
     fn as_string(&self) -> String {
         format!("{:?}", self)
@@ -37,10 +69,10 @@ pub trait Material: Debug {
 
 #[derive(Debug)]
 /// A diffuse material, scatters light at random, with a color. It models a perfectly matte surface.
-/// The `albedo` is the color of the material.
+/// The `albedo` is the color of the material, which can be any [`Texture`].
 /// This has the most vibrarnt color of all the materials, as it reflects light in all directions.
 pub struct Lambertian {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
 }
 #[derive(Debug)]
 /// Almost Identical to the lambertian, but the color is dynamically determined by the normal vector at the hit point.
@@ -48,9 +80,9 @@ pub struct Normal {}
 #[derive(Debug)]
 /// A metal material, reflects light and imparts a slight color.
 /// The reflectance is determined by the fuzziness of the material, with higher values being more blurry, don't use negative values unless you want some weird results.
-/// The `albedo` is the color of the material, this generally looks like a tint of the reflected light.
+/// The `albedo` is the color of the material, this generally looks like a tint of the reflected light. It can be any [`Texture`].
 pub struct Metal {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
     fuzz: f64, //I could enforce a specific range, buts its funnier not to.
 }
 #[derive(Debug)]
@@ -58,16 +90,30 @@ pub struct Metal {
 pub struct Dielectric {
     ior: f64,
 }
+#[derive(Debug)]
+/// A light emitting material, it emits `emit` from both sides of the surface, and absorbs every ray that hits it.
+/// Emitted colors can ( and usually should ) go above 1.0, brighter lights light up the scene more.
+pub struct DiffuseLight {
+    emit: Arc<dyn Texture>,
+}
 
 impl Metal {
     /// Creates a new `Metal` material with the given albedo and fuzziness.
     pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Metal::from_texture(Arc::new(albedo), fuzz)
+    }
+    /// Creates a new `Metal` material with a textured albedo and the given fuzziness.
+    pub fn from_texture(albedo: Arc<dyn Texture>, fuzz: f64) -> Self {
         Metal { albedo, fuzz }
     }
 }
 impl Lambertian {
     /// Creates a new `Lambertian` material with the given albedo.
     pub fn new(albedo: Color) -> Self {
+        Lambertian::from_texture(Arc::new(albedo))
+    }
+    /// Creates a new `Lambertian` material with a textured albedo.
+    pub fn from_texture(albedo: Arc<dyn Texture>) -> Self {
         Lambertian { albedo }
     }
 }
@@ -77,6 +123,16 @@ impl Dielectric {
         Dielectric { ior }
     }
 }
+impl DiffuseLight {
+    /// Creates a new `DiffuseLight` material that emits the given color.
+    pub fn new(emit: Color) -> Self {
+        DiffuseLight::from_texture(Arc::new(emit))
+    }
+    /// Creates a new `DiffuseLight` material that emits a texture.
+    pub fn from_texture(emit: Arc<dyn Texture>) -> Self {
+        DiffuseLight { emit }
+    }
+}
 impl Normal {
     /// Creates a new `Normal` material.
     pub fn new() -> Self {
@@ -91,8 +147,9 @@ impl Material for Lambertian {
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        let mut scatter_direction = rec.normal + (Vec3::random_normalized()); //on hit, send the ray in a random direction ( on the surface of the sphere )
+        let mut scatter_direction = rec.normal + (Vec3::random_normalized(sampler)); //on hit, send the ray in a random direction ( on the surface of the sphere )
 
         //Checks to make sure the direction isnt too close to 0, which causes artfacting
         if scatter_direction.near_zero() {
@@ -101,9 +158,25 @@ impl Material for Lambertian {
 
         *scattered = Ray::new(rec.p, scatter_direction); //send a new ray in the sactter direction
                                                          //from from hitpoint (rec.p)
-        *attenuation = self.albedo;
+        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
         true
     }
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let cos = dot(&rec.normal, &direction.normalized()).max(0.);
+        self.albedo.value(rec.u, rec.v, &rec.p) * (cos / PI)
+    }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p)
+    }
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        // the scattered rays are cosine weighted around the normal
+        dot(&rec.normal, &direction.normalized()).max(0.) / PI
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Lambertian {
+            albedo: self.albedo.describe()?,
+        })
+    }
 }
 
 impl Material for Normal {
@@ -113,11 +186,29 @@ impl Material for Normal {
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        let scatter_direction = rec.normal + (Vec3::random_normalized());
+        let mut scatter_direction = rec.normal + (Vec3::random_normalized(sampler));
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal
+        }
         *scattered = Ray::new(rec.p, scatter_direction);
-        *attenuation = Color::new(rec.normal.x, rec.normal.y, rec.normal.z);
-        false
+        *attenuation = self.albedo(rec);
+        true
+    }
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let cos = dot(&rec.normal, &direction.normalized()).max(0.);
+        self.albedo(rec) * (cos / PI)
+    }
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        dot(&rec.normal, &direction.normalized()).max(0.) / PI
+    }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        // each axis of the normal, from -1 to 1, becomes a channel from 0 to 1
+        (rec.normal + 1.) * 0.5
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Normal)
     }
 }
 
@@ -128,14 +219,24 @@ impl Material for Metal {
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         let reflected: Vec3 = r_in.direction.reflect(&rec.normal);
-        let reflected = reflected.normalized() + Vec3::random_normalized() * self.fuzz;
+        let reflected = reflected.normalized() + Vec3::random_normalized(sampler) * self.fuzz;
 
         *scattered = Ray::new(rec.p, reflected);
-        *attenuation = self.albedo;
+        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
         return dot(&scattered.direction, &rec.normal) > 0.;
     }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p)
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Metal {
+            albedo: self.albedo.describe()?,
+            fuzz: self.fuzz,
+        })
+    }
 }
 impl Material for Dielectric {
     fn scatter(
@@ -144,6 +245,7 @@ impl Material for Dielectric {
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         *attenuation = Color::new(1., 1., 1.);
 
@@ -159,7 +261,7 @@ impl Material for Dielectric {
 
         let cannot_refract: bool = ri * sin_theta > 1.0;
         let direction: Vec3;
-        if cannot_refract || reflectance(cos_theta, ri) > rand::thread_rng().gen_range(0.0..1.0) {
+        if cannot_refract || reflectance(cos_theta, ri) > sampler.next_1d() {
             direction = unit_direction.reflect(&rec.normal)
         } else {
             direction = refract(unit_direction, &rec.normal, ri)
@@ -169,6 +271,31 @@ impl Material for Dielectric {
 
         true
     }
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::from(1.)
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Dielectric { ior: self.ior })
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.emit.value(rec.u, rec.v, &rec.p)
+    }
+    fn is_emissive(&self) -> bool {
+        true
+    }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        // lights are as bright as a surface can be, or their own color if that's dimmer
+        let emit = self.emit.value(rec.u, rec.v, &rec.p);
+        Color::new(emit.x.min(1.), emit.y.min(1.), emit.z.min(1.))
+    }
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::DiffuseLight {
+            emit: self.emit.describe()?,
+        })
+    }
 }
 
 //schlick approximation for reflectance at grazing angles
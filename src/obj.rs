@@ -0,0 +1,387 @@
+//! This module loads Wavefront OBJ models, and their MTL material libraries, into a scene.
+//! Each material used by the model becomes one [`Mesh`] in the returned [`HittableList`], so the model can be added straight to a scene, or turned into a BVH first.
+//!
+//! Materials are mapped onto the ones in this crate as closely as they can be:
+//! - `Ke` ( emission ) above zero becomes a [`DiffuseLight`].
+//! - A `d` ( dissolve ) below 1, a `Tr` above 0, or `illum` 4, 6 or 7 becomes a [`Dielectric`], using `Ni` as its index of refraction.
+//! - `illum` 3, or a `Ks` ( specular color ) brighter than `Kd` becomes a [`Metal`] colored by `Ks`. Its fuzz comes from `Ns`, with sharper highlights making sharper reflections.
+//! - Anything else becomes a [`Lambertian`] colored by `Kd`.
+//!
+//! Faces with more than three vertices are split into triangles, and faces without a material use a plain gray [`Lambertian`].
+//! # Example
+//! ```no_run
+//! use rtwlib::{hittable::HittableList, obj};
+//!
+//! let model = obj::load_obj("teapot.obj").expect("failed to load model");
+//! let mut world = HittableList::new();
+//! world.add(model);
+//! ```
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+    fs, io,
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    color::Color,
+    hittable::{
+        mesh::{Mesh, MeshData},
+        HittableList,
+    },
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    vec3::*,
+};
+
+#[derive(Debug)]
+/// An error from loading an OBJ or MTL file.
+pub enum ObjError {
+    /// A file couldn't be read.
+    Io(io::Error),
+    /// A line of a file couldn't be understood.
+    Parse {
+        /// The line number the error is on, starting from 1
+        line: usize,
+        /// What was wrong with the line
+        message: String,
+    },
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(err) => write!(f, "{}", err),
+            ObjError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io(err) => Some(err),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(err: io::Error) -> Self {
+        ObjError::Io(err)
+    }
+}
+
+/// Loads an OBJ file, along with any MTL files it references, which are looked up relative to the OBJ file.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<HittableList, ObjError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut materials = HashMap::new();
+    for line in source.lines() {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() == Some("mtllib") {
+            for file in tokens {
+                materials.extend(parse_mtl(&fs::read_to_string(dir.join(file))?)?);
+            }
+        }
+    }
+    parse_obj(&source, &materials)
+}
+
+/// Parses the text of an OBJ file, looking up `usemtl` names in `materials`.
+/// `mtllib` lines are ignored, use [`load_obj`] to load them automatically.
+pub fn parse_obj(
+    source: &str,
+    materials: &HashMap<String, Arc<dyn Material>>,
+) -> Result<HittableList, ObjError> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+
+    let default_material: Arc<dyn Material> = Arc::new(Lambertian::new(Color::from(0.8)));
+    let mut groups: Vec<MeshBuilder> = Vec::new();
+    let mut current: Option<usize> = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => positions.push(parse_vec3(&args, number)?),
+            "vn" => normals.push(parse_vec3(&args, number)?),
+            "vt" => {
+                let uv = parse_floats(&args, number)?;
+                if uv.is_empty() {
+                    return Err(parse_error(number, "texture coordinate needs a u value"));
+                }
+                uvs.push((uv[0], uv.get(1).copied().unwrap_or(0.0)));
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                let mat = materials.get(&name).cloned();
+                current = Some(match groups.iter().position(|g| g.name == name) {
+                    Some(index) => index,
+                    None => {
+                        groups.push(MeshBuilder::new(
+                            name,
+                            mat.unwrap_or_else(|| default_material.clone()),
+                        ));
+                        groups.len() - 1
+                    }
+                });
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_error(number, "face needs at least 3 vertices"));
+                }
+                let corners = args
+                    .iter()
+                    .map(|arg| {
+                        parse_corner(arg, [positions.len(), uvs.len(), normals.len()], number)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let group = match current {
+                    Some(index) => &mut groups[index],
+                    None => {
+                        groups.push(MeshBuilder::new(String::new(), default_material.clone()));
+                        current = Some(groups.len() - 1);
+                        groups.last_mut().expect("group was just added")
+                    }
+                };
+                // split polygons into a fan of triangles around the first corner
+                for i in 1..corners.len() - 1 {
+                    group.add_triangle(
+                        [corners[0], corners[i], corners[i + 1]],
+                        &positions,
+                        &uvs,
+                        &normals,
+                    );
+                }
+            }
+            _ => {} // groups, smoothing groups, and anything else we don't need
+        }
+    }
+
+    let mut list = HittableList::new();
+    for group in groups {
+        if !group.data.indices.is_empty() {
+            list.add(group.build());
+        }
+    }
+    Ok(list)
+}
+
+/// Parses the text of an MTL file, and returns the materials it defines by name.
+pub fn parse_mtl(source: &str) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlProperties)> = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, props)) = current.take() {
+                materials.insert(name, props.to_material());
+            }
+            current = Some((args.join(" "), MtlProperties::default()));
+            continue;
+        }
+        let Some((_, props)) = current.as_mut() else {
+            continue; // properties before the first material don't belong to anything
+        };
+        match keyword {
+            "Kd" => props.diffuse = parse_vec3(&args, number)?,
+            "Ks" => props.specular = parse_vec3(&args, number)?,
+            "Ke" => props.emission = parse_vec3(&args, number)?,
+            "Ns" => props.shininess = parse_float(&args, number)?,
+            "Ni" => props.ior = parse_float(&args, number)?,
+            "d" => props.dissolve = parse_float(&args, number)?,
+            "Tr" => props.dissolve = 1.0 - parse_float(&args, number)?,
+            "illum" => props.illum = parse_float(&args, number)? as u32,
+            _ => {} // texture maps and anything else we can't use
+        }
+    }
+    if let Some((name, props)) = current {
+        materials.insert(name, props.to_material());
+    }
+    Ok(materials)
+}
+
+/// The properties of an MTL material that are used to pick a material.
+struct MtlProperties {
+    diffuse: Color,
+    specular: Color,
+    emission: Color,
+    shininess: f64,
+    ior: f64,
+    dissolve: f64,
+    illum: u32,
+}
+
+impl Default for MtlProperties {
+    fn default() -> Self {
+        MtlProperties {
+            diffuse: Color::from(0.8),
+            specular: Color::from(0.0),
+            emission: Color::from(0.0),
+            shininess: 0.0,
+            ior: 1.5,
+            dissolve: 1.0,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlProperties {
+    fn to_material(&self) -> Arc<dyn Material> {
+        let brightness = |c: &Color| c.x.max(c.y).max(c.z);
+
+        if brightness(&self.emission) > 0.0 {
+            Arc::new(DiffuseLight::new(self.emission))
+        } else if self.dissolve < 1.0 || matches!(self.illum, 4 | 6 | 7) {
+            Arc::new(Dielectric::new(self.ior))
+        } else if self.illum == 3 || brightness(&self.specular) > brightness(&self.diffuse) {
+            // phong exponents go from 0 ( rough ) to 1000 ( mirror-like )
+            let fuzz = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+            Arc::new(Metal::new(self.specular, fuzz))
+        } else {
+            Arc::new(Lambertian::new(self.diffuse))
+        }
+    }
+}
+
+/// The position, UV and normal index of one corner of a face, the last two are optional.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// Collects the faces of one material into a mesh, sharing vertices with the same indices.
+struct MeshBuilder {
+    name: String,
+    mat: Arc<dyn Material>,
+    data: MeshData,
+    uvs: Vec<Option<(f64, f64)>>,
+    normals: Vec<Option<Vec3>>,
+    face_normals: Vec<Vec3>,
+    vertices: HashMap<Corner, usize>,
+}
+
+impl MeshBuilder {
+    fn new(name: String, mat: Arc<dyn Material>) -> Self {
+        MeshBuilder {
+            name,
+            mat,
+            data: MeshData::default(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            face_normals: Vec::new(),
+            vertices: HashMap::new(),
+        }
+    }
+
+    fn add_triangle(
+        &mut self,
+        corners: [Corner; 3],
+        positions: &[Point3],
+        uvs: &[(f64, f64)],
+        normals: &[Vec3],
+    ) {
+        let [a, b, c] = corners.map(|corner| positions[corner.0]);
+        let face_normal = cross(&(b - a), &(c - a)).normalized();
+
+        let indices = corners.map(|corner| {
+            *self.vertices.entry(corner).or_insert_with(|| {
+                self.data.positions.push(positions[corner.0]);
+                self.uvs.push(corner.1.map(|i| uvs[i]));
+                self.normals.push(corner.2.map(|i| normals[i]));
+                self.face_normals.push(face_normal);
+                self.data.positions.len() - 1
+            })
+        });
+        self.data.indices.push(indices);
+    }
+
+    /// Builds the mesh, vertices missing a normal or uv get the face normal or (0, 0), if other vertices have them.
+    fn build(mut self) -> Mesh {
+        if self.uvs.iter().any(Option::is_some) {
+            self.data.uvs = self.uvs.iter().map(|uv| uv.unwrap_or((0.0, 0.0))).collect();
+        }
+        if self.normals.iter().any(Option::is_some) {
+            self.data.normals = self
+                .normals
+                .iter()
+                .zip(&self.face_normals)
+                .map(|(n, face)| n.unwrap_or(*face))
+                .collect();
+        }
+        Mesh::new(self.data, self.mat)
+    }
+}
+
+fn parse_error(line: usize, message: &str) -> ObjError {
+    ObjError::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
+
+fn parse_floats(args: &[&str], line: usize) -> Result<Vec<f64>, ObjError> {
+    args.iter()
+        .map(|arg| {
+            arg.parse::<f64>()
+                .map_err(|_| parse_error(line, &format!("invalid number '{}'", arg)))
+        })
+        .collect()
+}
+
+fn parse_float(args: &[&str], line: usize) -> Result<f64, ObjError> {
+    parse_floats(args, line)?
+        .first()
+        .copied()
+        .ok_or_else(|| parse_error(line, "expected a number"))
+}
+
+fn parse_vec3(args: &[&str], line: usize) -> Result<Vec3, ObjError> {
+    let values = parse_floats(args, line)?;
+    match values.as_slice() {
+        [x, y, z, ..] => Ok(Vec3::new(*x, *y, *z)),
+        [v] => Ok(Vec3::from(*v)), // MTL colors can be a single grayscale value
+        _ => Err(parse_error(line, "expected 3 numbers")),
+    }
+}
+
+/// Parses a face corner like `1`, `1/2`, `1//3` or `1/2/3`, turning 1-based or negative indices into 0-based ones.
+/// `counts` are the number of positions, UVs and normals loaded so far.
+fn parse_corner(arg: &str, counts: [usize; 3], line: usize) -> Result<Corner, ObjError> {
+    let mut parts = arg.split('/');
+    let mut index = |kind: usize| -> Result<Option<usize>, ObjError> {
+        let part = match parts.next() {
+            Some(part) if !part.is_empty() => part,
+            _ => return Ok(None),
+        };
+        let invalid = || parse_error(line, &format!("invalid vertex index '{}'", arg));
+        let i: i64 = part.parse().map_err(|_| invalid())?;
+        let count = counts[kind] as i64;
+        let resolved = if i < 0 { count + i } else { i - 1 };
+        if resolved < 0 || resolved >= count {
+            return Err(invalid());
+        }
+        Ok(Some(resolved as usize))
+    };
+
+    let position = index(0)?.ok_or_else(|| parse_error(line, "face vertex needs a position"))?;
+    let uv = index(1)?;
+    let normal = index(2)?;
+    Ok((position, uv, normal))
+}
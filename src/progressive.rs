@@ -0,0 +1,183 @@
+//! This module contains the [`ProgressiveRenderer`], which renders an image a few samples at a time instead of all at once.
+//! The premade render functions on [`Camera`] only return once every sample is done, which can take a long time. A progressive renderer keeps the samples it has traced so far, and each call to [`ProgressiveRenderer::render_pass`] adds a few more to every pixel, so the image starts out noisy and cleans up over time.
+//! This is made for interactive frontends, which can show the image after every pass, and stop whenever they like.
+//!
+//! Passes continue the sample numbers where the last one stopped, so rendering 4 passes of 8 samples traces exactly the same rays as one render with 32 samples and the same seed, and gives the same image ( apart from tiny float rounding differences ).
+//!
+//! The render so far can be saved as a [`Checkpoint`] between passes, and picked up again later with [`ProgressiveRenderer::resume`], see the [`checkpoint`](crate::checkpoint) module.
+//!
+//! # Example
+//! ```
+//! use rtwlib::{camera::Camera, hittable::HittableList, progressive::ProgressiveRenderer};
+//!
+//! let mut cam = Camera::new();
+//! (cam.image_width, cam.image_height, cam.samples) = (32, 18, 16);
+//! let mut renderer = ProgressiveRenderer::new(cam, HittableList::new());
+//!
+//! while !renderer.is_done() {
+//!     renderer.render_pass(4);
+//!     let preview = renderer.to_rgb_bytes();
+//!     // show the preview...
+//! }
+//! assert_eq!(renderer.samples(), 16);
+//! ```
+use crate::{
+    camera::Camera,
+    checkpoint::{Checkpoint, CheckpointError},
+    film::Film,
+    hittable::HittableList,
+    stable_hash::{camera_film_hash, world_film_hash},
+    tile::Tile,
+};
+use std::sync::OnceLock;
+
+/// Renders a scene in passes, keeping an accumulation [`Film`] with every sample traced so far.
+/// The camera's `samples` is the target sample count, used by [`ProgressiveRenderer::is_done`], passes can keep going past it.
+pub struct ProgressiveRenderer {
+    camera: Camera,
+    world: HittableList,
+    film: Film,
+    samples: u32,
+    /// The part of the scene hash from the objects, found the first time a checkpoint needs it, `None` if they can't be described
+    world_hash: OnceLock<Option<u64>>,
+}
+
+impl ProgressiveRenderer {
+    /// Creates a new `ProgressiveRenderer`, with a black image and no samples. The camera is initialized, so it doesn't need to be done beforehand.
+    pub fn new(mut camera: Camera, world: HittableList) -> Self {
+        camera.initialize();
+        ProgressiveRenderer {
+            film: Film::with_filter(camera.image_width, camera.image_height, camera.filter),
+            world_hash: OnceLock::new(),
+            camera,
+            world,
+            samples: 0,
+        }
+    }
+    /// Creates a `ProgressiveRenderer` that carries on from a checkpoint, so the next pass continues where it stopped.
+    /// Returns [`CheckpointError::SceneChanged`] if the checkpoint was rendered with a different scene, camera or seed, or without one of the camera's AOVs. The camera's `samples` can be different, to keep adding samples to a finished render.
+    /// # Example
+    /// A render that is stopped and resumed gives exactly the same image as one that wasn't:
+    /// ```
+    /// use rtwlib::{camera::Camera, checkpoint::Checkpoint, color::Color, hittable::{sphere::Sphere, HittableList}, material::Lambertian, progressive::ProgressiveRenderer, vec3::Point3};
+    /// use std::sync::Arc;
+    ///
+    /// let scene = || {
+    ///     let mut world = HittableList::new();
+    ///     let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+    ///     world.add(Sphere::new(Point3::new(0., 0., -1.), 0.5, material));
+    ///     let mut cam = Camera::new();
+    ///     (cam.image_width, cam.image_height, cam.samples) = (24, 16, 8);
+    ///     (cam, world)
+    /// };
+    ///
+    /// let (cam, world) = scene();
+    /// let mut straight = ProgressiveRenderer::new(cam, world);
+    /// while !straight.is_done() {
+    ///     straight.render_pass(2);
+    /// }
+    ///
+    /// let (cam, world) = scene();
+    /// let mut stopped = ProgressiveRenderer::new(cam, world);
+    /// stopped.render_pass(2);
+    /// stopped.render_pass(2);
+    /// let mut file = Vec::new();
+    /// stopped.checkpoint().write(&mut file).unwrap();
+    ///
+    /// let (cam, world) = scene();
+    /// let checkpoint = Checkpoint::read(&mut file.as_slice(), &cam.aovs).unwrap();
+    /// let mut resumed = ProgressiveRenderer::resume(cam, world, checkpoint).unwrap();
+    /// while !resumed.is_done() {
+    ///     resumed.render_pass(2);
+    /// }
+    ///
+    /// let bits = |renderer: &ProgressiveRenderer| -> Vec<u64> {
+    ///     renderer.film().pixels().iter().flat_map(|c| [c.x, c.y, c.z].map(f64::to_bits)).collect()
+    /// };
+    /// assert_eq!(resumed.samples(), 8);
+    /// assert_eq!(bits(&resumed), bits(&straight));
+    /// ```
+    pub fn resume(
+        camera: Camera,
+        world: HittableList,
+        checkpoint: Checkpoint,
+    ) -> Result<Self, CheckpointError> {
+        let mut renderer = ProgressiveRenderer::new(camera, world);
+        let film = checkpoint.film();
+        if checkpoint.seed() != renderer.camera.seed
+            || (film.width(), film.height(), film.origin())
+                != (renderer.camera.image_width, renderer.camera.image_height, (0, 0))
+            // new passes are spread with the camera's filter, so the old ones have to be too
+            || film.filter() != renderer.camera.filter
+            // a scene that can't be described can only resume checkpoints without a hash, and the other way around
+            || renderer.scene_hash() != checkpoint.scene_hash()
+            // a new layer would only get the new samples
+            || (checkpoint.samples() > 0
+                && !renderer.camera.aovs.iter().all(|aov| film.aovs().contains(aov)))
+        {
+            return Err(CheckpointError::SceneChanged);
+        }
+        renderer.samples = checkpoint.samples();
+        renderer.film = checkpoint.into_film();
+        Ok(renderer)
+    }
+    /// Traces `samples` more samples through every pixel, and adds them to the image. Blocks until the pass is done.
+    pub fn render_pass(&mut self, samples: u32) {
+        let range = self.samples..self.samples.saturating_add(samples);
+        let image = Tile::new(0, 0, self.camera.image_width, self.camera.image_height);
+        let pass =
+            self.camera
+                .render_film(&self.world, image, range.clone(), Some(&self.film), |_| {});
+        self.film.merge(&pass);
+        self.samples = range.end;
+    }
+    /// Returns the number of samples traced through each pixel so far.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+    /// Returns true once the camera's target number of samples has been reached.
+    pub fn is_done(&self) -> bool {
+        self.samples >= self.camera.samples
+    }
+    /// Returns the image so far, the average of every sample traced in each pixel.
+    pub fn film(&self) -> &Film {
+        &self.film
+    }
+    /// Encodes the image so far with the camera's output settings, in the same `[R, G, B, R, G, B, ...]` layout as `Camera::render_to_bytes`.
+    pub fn to_rgb_bytes(&self) -> Vec<u8> {
+        self.film.to_rgb_bytes(&self.camera.output_transform())
+    }
+    /// Returns a checkpoint of the render so far, which can be saved, and resumed later with [`ProgressiveRenderer::resume`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint::new(
+            self.film.clone(),
+            self.samples,
+            self.camera.seed,
+            self.scene_hash(),
+        )
+    }
+    /// Returns the camera used to render.
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+    /// Replaces the camera, for example when the view moves, and starts the image over.
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+        self.reset();
+    }
+    /// Returns the `film_hash` of the scene, for checkpoints, or `None` if it can't be described.
+    fn scene_hash(&self) -> Option<u64> {
+        let world = (*self.world_hash.get_or_init(|| world_film_hash(&self.world)))?;
+        camera_film_hash(&self.camera, world)
+    }
+    /// Throws away every sample, starting the image over from black.
+    pub fn reset(&mut self) {
+        self.camera.initialize();
+        self.film = Film::with_filter(
+            self.camera.image_width,
+            self.camera.image_height,
+            self.camera.filter,
+        );
+        self.samples = 0;
+    }
+}
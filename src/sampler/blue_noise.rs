@@ -0,0 +1,176 @@
+//! A blue noise sampler, using a void and cluster mask.
+
+use std::sync::OnceLock;
+
+use super::{hash, RandomSampler, Sampler};
+
+/// The width and height of the blue noise mask, in pixels. The mask repeats over bigger images.
+const MASK_SIZE: usize = 64;
+
+/// The golden ratio, and the plastic number ( its 2D version ), used to step through the samples of a pixel.
+const GOLDEN: f64 = 1.618_033_988_749_895;
+const PLASTIC: f64 = 1.324_717_957_244_746;
+
+#[derive(Clone, Debug)]
+/// A blue noise sampler, which makes neighboring pixels use very different numbers, so the noise left in a render is fine grained and even, instead of blotchy.
+/// The first sample of each pixel comes from a blue noise mask, a tiled image where each value is as far as possible from similar values. Each dimension reads the mask at a different offset.
+/// Later samples step away from it along the golden ratio ( R1 ) or plastic number ( R2 ) sequences, which stay evenly spread within the pixel, and keep the error between pixels blue.
+pub struct BlueNoiseSampler {
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl BlueNoiseSampler {
+    /// Creates a new `BlueNoiseSampler`.
+    /// The mask is generated the first time it's used, which takes a moment.
+    pub fn new(seed: u64) -> Self {
+        BlueNoiseSampler {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Reads the mask for the next dimension, returning 2 values from different parts of it.
+    fn next_mask_values(&mut self) -> (f64, f64) {
+        // the offsets only depend on the dimension, so the mask isn't broken up between neighboring pixels
+        let offsets = hash(self.seed, (0, 0), self.dimension as u64);
+        self.dimension += 1;
+        let mask = blue_noise_mask();
+        let read = |offset: u64| {
+            let x = (self.pixel.0 as usize + (offset as u16) as usize) % MASK_SIZE;
+            let y = (self.pixel.1 as usize + (offset >> 16) as u16 as usize) % MASK_SIZE;
+            (mask[y * MASK_SIZE + x] as f64 + 0.5) / (MASK_SIZE * MASK_SIZE) as f64
+        };
+        (read(offsets), read(offsets >> 32))
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+    fn next_1d(&mut self) -> f64 {
+        let (value, _) = self.next_mask_values();
+        (value + self.index as f64 / GOLDEN).fract()
+    }
+    fn next_2d(&mut self) -> (f64, f64) {
+        let (x, y) = self.next_mask_values();
+        let index = self.index as f64;
+        (
+            (x + index / PLASTIC).fract(),
+            (y + index / (PLASTIC * PLASTIC)).fract(),
+        )
+    }
+}
+
+/// Returns the blue noise mask, generating it the first time. Each pixel holds its rank, from 0 to `MASK_SIZE * MASK_SIZE - 1`.
+fn blue_noise_mask() -> &'static [u16] {
+    static MASK: OnceLock<Vec<u16>> = OnceLock::new();
+    MASK.get_or_init(void_and_cluster)
+}
+
+/// Builds a blue noise mask with Robert Ulichney's void and cluster method.
+/// Pixels are turned on one at a time, each one in the biggest gap ( void ) between the pixels already on, and ranked in that order.
+fn void_and_cluster() -> Vec<u16> {
+    const PIXELS: usize = MASK_SIZE * MASK_SIZE;
+    let mut pattern = Pattern::new();
+
+    // start with a few random pixels, and move them around until they are evenly spread:
+    // the pixel in the tightest cluster is moved to the biggest void, until it would land where it started
+    let mut rng = RandomSampler::new(0xb1ce);
+    while pattern.count < PIXELS / 10 {
+        let pixel = (rng.next_1d() * PIXELS as f64) as usize;
+        if !pattern.on[pixel] {
+            pattern.toggle(pixel);
+        }
+    }
+    // this settles quickly, the limit is just in case it ends up going in circles
+    for _ in 0..PIXELS {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+        let void = pattern.biggest_void();
+        pattern.toggle(void);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; PIXELS];
+    // the starting pixels are ranked by taking them away, tightest cluster first
+    let mut removing = pattern.clone();
+    while removing.count > 0 {
+        let cluster = removing.tightest_cluster();
+        removing.toggle(cluster);
+        ranks[cluster] = removing.count as u16;
+    }
+    // and every other pixel by filling the biggest void
+    while pattern.count < PIXELS {
+        let void = pattern.biggest_void();
+        ranks[void] = pattern.count as u16;
+        pattern.toggle(void);
+    }
+    ranks
+}
+
+#[derive(Clone)]
+/// A binary pattern used to build the mask, with the energy at each pixel: how crowded it is by pixels that are on, using a gaussian falloff.
+struct Pattern {
+    on: Vec<bool>,
+    energy: Vec<f64>,
+    count: usize,
+}
+
+impl Pattern {
+    /// How far the energy of a pixel reaches, the gaussian is close enough to 0 past this.
+    const REACH: i64 = 6;
+    const SIGMA: f64 = 1.5;
+
+    fn new() -> Self {
+        Pattern {
+            on: vec![false; MASK_SIZE * MASK_SIZE],
+            energy: vec![0.0; MASK_SIZE * MASK_SIZE],
+            count: 0,
+        }
+    }
+    /// Turns a pixel on or off, updating the energy around it. The mask wraps around, so it can be tiled.
+    fn toggle(&mut self, pixel: usize) {
+        self.on[pixel] = !self.on[pixel];
+        let sign = if self.on[pixel] {
+            self.count += 1;
+            1.0
+        } else {
+            self.count -= 1;
+            -1.0
+        };
+        let (x, y) = ((pixel % MASK_SIZE) as i64, (pixel / MASK_SIZE) as i64);
+        for dy in -Self::REACH..=Self::REACH {
+            for dx in -Self::REACH..=Self::REACH {
+                let falloff =
+                    (-((dx * dx + dy * dy) as f64) / (2.0 * Self::SIGMA * Self::SIGMA)).exp();
+                let px = (x + dx).rem_euclid(MASK_SIZE as i64) as usize;
+                let py = (y + dy).rem_euclid(MASK_SIZE as i64) as usize;
+                self.energy[py * MASK_SIZE + px] += sign * falloff;
+            }
+        }
+    }
+    /// The pixel that is on with the most energy.
+    fn tightest_cluster(&self) -> usize {
+        (0..self.on.len())
+            .filter(|&i| self.on[i])
+            .max_by(|&a, &b| self.energy[a].total_cmp(&self.energy[b]))
+            .expect("the pattern is empty")
+    }
+    /// The pixel that is off with the least energy.
+    fn biggest_void(&self) -> usize {
+        (0..self.on.len())
+            .filter(|&i| !self.on[i])
+            .min_by(|&a, &b| self.energy[a].total_cmp(&self.energy[b]))
+            .expect("the pattern is full")
+    }
+}
@@ -0,0 +1,67 @@
+//! A sampler using the Halton sequence.
+
+use super::{hash, to_unit, Sampler};
+
+/// The bases of the dimensions that use the Halton sequence, the first 32 primes.
+/// Larger bases need a lot of samples to fill the dimension evenly, so later dimensions fall back to random numbers.
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+#[derive(Clone, Debug)]
+/// A sampler using the Halton sequence, where each dimension is the radical inverse of the sample number in a different prime base.
+/// Every pixel runs through the same sequence, shifted by a random amount for each pixel and dimension ( a Cranley-Patterson rotation ), so neighboring pixels don't share the same pattern.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl HaltonSampler {
+    /// Creates a new `HaltonSampler`.
+    pub fn new(seed: u64) -> Self {
+        HaltonSampler {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.dimension as u64;
+        self.dimension += 1;
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => {
+                let shift = to_unit(hash(self.seed, self.pixel, dimension) as u32);
+                (radical_inverse(base, self.index) + shift).fract()
+            }
+            None => {
+                let n = ((self.index as u64) << 32) | dimension;
+                to_unit(hash(self.seed, self.pixel, n) as u32)
+            }
+        }
+    }
+}
+
+/// Mirrors the digits of `i` in `base` around the decimal point, so 1, 2, 3, ... in base 2 becomes 0.1, 0.01, 0.11, ...
+fn radical_inverse(base: u32, mut i: u32) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut scale = inverse_base;
+    let mut result = 0.0;
+    while i > 0 {
+        result += (i % base) as f64 * scale;
+        i /= base;
+        scale *= inverse_base;
+    }
+    result
+}
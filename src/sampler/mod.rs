@@ -0,0 +1,173 @@
+//! This module contains the `Sampler` trait, and the samplers used by the renderer.
+//! Every random choice made while rendering ( where a ray goes in a pixel, how a material scatters it, ... ) comes from a sampler, which is passed down through `Camera::ray_color` and `Material::scatter`.
+//!
+//! The camera restarts its sampler for every sample of every pixel, from the camera's `seed`, the pixel and the sample number. This makes renders repeatable: the same seed gives exactly the same image, no matter how many threads render it or in what order.
+//!
+//! Each call to `next_1d` or `next_2d` in a sample is a "dimension". Plain random numbers leave clumps and gaps between the samples of a pixel, so the other samplers spread the samples of each dimension out evenly, which makes renders converge faster:
+//! - [`RandomSampler`]: Plain random numbers from PCG32.
+//! - [`StratifiedSampler`]: Splits each dimension into one cell per sample, and puts a random point in each cell.
+//! - [`HaltonSampler`]: The Halton sequence, randomly shifted for each pixel.
+//! - [`SobolSampler`]: The Sobol sequence, shuffled and scrambled for each pixel and dimension.
+//! - [`BlueNoiseSampler`]: Spreads the error between neighboring pixels evenly, so the noise that is left looks fine grained instead of blotchy.
+//!
+//! The camera picks one with its `sampler` setting, see [`SamplerKind`].
+//!
+//! # Example
+//! ```
+//! use rtwlib::{sampler::{RandomSampler, Sampler}, vec3::Vec3};
+//!
+//! let mut sampler = RandomSampler::new(42);
+//! let direction = Vec3::random_normalized(&mut sampler);
+//!
+//! // restarting a sample gives the same numbers again
+//! sampler.start_sample((10, 20), 0);
+//! let a = sampler.next_1d();
+//! sampler.start_sample((10, 20), 0);
+//! assert_eq!(a, sampler.next_1d());
+//! ```
+mod blue_noise;
+mod halton;
+mod sobol;
+mod stratified;
+
+pub use blue_noise::BlueNoiseSampler;
+pub use halton::HaltonSampler;
+pub use sobol::SobolSampler;
+pub use stratified::StratifiedSampler;
+
+/// A source of random numbers for rendering.
+/// Samplers hand out numbers one sample at a time: `start_sample` is called before each sample of each pixel, then `next_1d` and `next_2d` are called for each random choice the sample makes.
+pub trait Sampler {
+    /// Starts sample number `index` of a pixel. Restarting the same sample gives the same numbers.
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32);
+    /// Returns a number between 0 and 1 ( not including 1 ).
+    fn next_1d(&mut self) -> f64;
+    /// Returns two numbers between 0 and 1, used for choices with two dimensions, like a point in a pixel.
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+    /// Returns a number in a range, like `rand`'s `gen_range`.
+    fn gen_range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_1d()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+/// The kinds of sampler the camera can render with.
+pub enum SamplerKind {
+    /// Plain random numbers, see [`RandomSampler`].
+    Random,
+    /// Jittered cells, see [`StratifiedSampler`].
+    Stratified,
+    /// The Halton sequence, see [`HaltonSampler`].
+    Halton,
+    /// The scrambled Sobol sequence, see [`SobolSampler`]. This is usually the fastest to converge.
+    #[default]
+    Sobol,
+    /// Blue noise, see [`BlueNoiseSampler`].
+    BlueNoise,
+}
+
+impl SamplerKind {
+    /// Creates a sampler of this kind, for a render with `samples` samples per pixel.
+    pub fn build(self, seed: u64, samples: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Random => Box::new(RandomSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(seed, samples)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+            SamplerKind::BlueNoise => Box::new(BlueNoiseSampler::new(seed)),
+        }
+    }
+    /// Returns the number the kind is stored as in scene hashes. These can't change, or old checkpoints stop resuming.
+    pub(crate) fn tag(self) -> u8 {
+        match self {
+            SamplerKind::Random => 0,
+            SamplerKind::Stratified => 1,
+            SamplerKind::Halton => 2,
+            SamplerKind::Sobol => 3,
+            SamplerKind::BlueNoise => 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A plain random sampler, using the PCG32 random number generator.
+/// Every sample gets its own stream of numbers, picked by hashing the seed, pixel and sample number.
+pub struct RandomSampler {
+    seed: u64,
+    rng: Pcg32,
+}
+
+impl RandomSampler {
+    /// Creates a new `RandomSampler`, which can also be used as a plain random number generator without calling `start_sample`.
+    pub fn new(seed: u64) -> Self {
+        RandomSampler {
+            seed,
+            rng: Pcg32::new(mix(seed), 0),
+        }
+    }
+}
+
+impl Sampler for RandomSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32) {
+        let hash = hash(self.seed, pixel, index as u64);
+        self.rng = Pcg32::new(hash, mix(hash));
+    }
+    fn next_1d(&mut self) -> f64 {
+        to_unit(self.rng.next_u32())
+    }
+}
+
+#[derive(Clone, Debug)]
+/// The PCG32 generator from Melissa O'Neill's PCG paper, small, fast and statistically very good.
+struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Pcg32 {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+}
+
+/// The SplitMix64 finalizer, scrambles the bits of a number so similar inputs give very different outputs.
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Hashes a seed, a pixel and one more number into a well mixed number.
+pub(crate) fn hash(seed: u64, (x, y): (u32, u32), n: u64) -> u64 {
+    let pixel = ((x as u64) << 32) | y as u64;
+    mix(seed ^ mix(pixel ^ mix(n)))
+}
+
+/// Turns 32 random bits into a number between 0 and 1 ( not including 1 ).
+fn to_unit(bits: u32) -> f64 {
+    bits as f64 / (1u64 << 32) as f64
+}
@@ -0,0 +1,85 @@
+//! A sampler using the Owen scrambled Sobol sequence.
+
+use super::{hash, mix, to_unit, Sampler};
+
+#[derive(Clone, Debug)]
+/// A sampler using the Sobol sequence, with Owen scrambling.
+/// Each `next_2d` call uses the first two Sobol dimensions, which are very evenly spread in 2D, and `next_1d` uses the first.
+/// To stop the dimensions from lining up with each other, each one shuffles the order of the samples and scrambles their digits with its own hash ( Brent Burley's "Practical Hash-based Owen Scrambling" ).
+/// This keeps the sequence evenly spread, while every pixel and dimension gets a different set of points.
+pub struct SobolSampler {
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    /// Creates a new `SobolSampler`.
+    pub fn new(seed: u64) -> Self {
+        SobolSampler {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Returns the shuffled sample number for the next dimension, and two seeds to scramble its values.
+    fn next_dimension(&mut self) -> (u32, u32, u32) {
+        let hash = hash(self.seed, self.pixel, self.dimension as u64);
+        self.dimension += 1;
+        let index = owen_scramble(self.index, hash as u32);
+        (index, (hash >> 32) as u32, mix(hash) as u32)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+    fn next_1d(&mut self) -> f64 {
+        let (index, seed, _) = self.next_dimension();
+        to_unit(owen_scramble(index.reverse_bits(), seed))
+    }
+    fn next_2d(&mut self) -> (f64, f64) {
+        let (index, seed_x, seed_y) = self.next_dimension();
+        (
+            to_unit(owen_scramble(index.reverse_bits(), seed_x)),
+            to_unit(owen_scramble(sobol_second(index), seed_y)),
+        )
+    }
+}
+
+/// The second dimension of the Sobol sequence, the first is just the bits of `i` reversed.
+fn sobol_second(mut i: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1 << 31;
+    while i != 0 {
+        if i & 1 != 0 {
+            result ^= direction;
+        }
+        i >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+/// Scrambles the binary digits of `x` ( a fraction, with the first digit in the highest bit ), so each digit is flipped depending on the digits before it.
+/// Scrambling a Sobol sequence this way keeps it just as evenly spread.
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// A hash where each bit only depends on the bits below it, the core of the Owen scramble.
+/// This is Nathan Vegdahl's improved version of the Laine-Karras permutation.
+fn laine_karras(mut x: u32, seed: u32) -> u32 {
+    x ^= x.wrapping_mul(0x3d20adea);
+    x = x.wrapping_add(seed);
+    x = x.wrapping_mul((seed >> 16) | 1);
+    x ^= x.wrapping_mul(0x05526c56);
+    x ^= x.wrapping_mul(0x53a22864);
+    x
+}
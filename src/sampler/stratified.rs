@@ -0,0 +1,118 @@
+//! A stratified ( jittered ) sampler.
+
+use super::{hash, mix, to_unit, Sampler};
+
+#[derive(Clone, Debug)]
+/// A stratified sampler, which splits each dimension into one cell ( stratum ) per sample, and puts each sample at a random point in its own cell.
+/// 2D dimensions are split into a grid, so the samples of a pixel cover it evenly instead of clumping together.
+/// The cells are handed out in a different random order for each pixel and dimension, so the dimensions don't line up with each other.
+/// # Example
+/// Every sample of a pixel lands in its own cell, in every dimension:
+/// ```
+/// use rtwlib::sampler::{Sampler, StratifiedSampler};
+///
+/// // a pixel of a 1600x900 image where one of the shuffles is close to overflowing
+/// let mut sampler = StratifiedSampler::new(0, 250);
+/// let mut cells = vec![[false; 250]; 9];
+/// for index in 0..250 {
+///     sampler.start_sample((556, 628), index);
+///     for dimension in cells.iter_mut() {
+///         let cell = (sampler.next_1d() * 250.0) as usize;
+///         assert!(!dimension[cell], "two samples in cell {}", cell);
+///         dimension[cell] = true;
+///     }
+/// }
+/// ```
+pub struct StratifiedSampler {
+    seed: u64,
+    samples: u32,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl StratifiedSampler {
+    /// Creates a new `StratifiedSampler`, for `samples` samples per pixel. Samples past that start a new, differently shuffled round of cells.
+    pub fn new(seed: u64, samples: u32) -> Self {
+        StratifiedSampler {
+            seed,
+            samples: samples.max(1),
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Picks the cell of the current sample, out of `cells`, and two random numbers to jitter the sample inside it.
+    fn next_cell(&mut self, cells: u32) -> (u32, u32, u32) {
+        let dimension = self.dimension as u64;
+        self.dimension += 1;
+        let round = (self.index / cells) as u64;
+        let shuffle = hash(self.seed, self.pixel, mix(dimension) ^ round) as u32;
+        let cell = permute(self.index % cells, cells, shuffle);
+        let jitter = hash(
+            !self.seed,
+            self.pixel,
+            ((self.index as u64) << 32) | dimension,
+        );
+        (cell, jitter as u32, (jitter >> 32) as u32)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+    fn next_1d(&mut self) -> f64 {
+        let (cell, jitter, _) = self.next_cell(self.samples);
+        (cell as f64 + to_unit(jitter)) / self.samples as f64
+    }
+    fn next_2d(&mut self) -> (f64, f64) {
+        // the grid is as square as possible, with at least one cell per sample
+        let columns = (self.samples as f64).sqrt() as u32;
+        let rows = self.samples.div_ceil(columns);
+        let (cell, jitter_x, jitter_y) = self.next_cell(columns * rows);
+        (
+            ((cell % columns) as f64 + to_unit(jitter_x)) / columns as f64,
+            ((cell / columns) as f64 + to_unit(jitter_y)) / rows as f64,
+        )
+    }
+}
+
+/// Maps `i` to a position in a random permutation of `0..len`, picked by `seed`.
+/// This is Andrew Kensler's hashed permutation from "Correlated Multi-Jittered Sampling", which shuffles without storing the permutation.
+fn permute(mut i: u32, len: u32, seed: u32) -> u32 {
+    // a bit mask covering len - 1, out of range results are permuted again until they fit
+    let mut mask = len - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & mask) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & mask) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & mask) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= mask;
+        i ^= i >> 5;
+        if i < len {
+            // in 64 bits, since wrapping around 2^32 would break the rotation
+            return ((i as u64 + seed as u64) % len as u64) as u32;
+        }
+    }
+}
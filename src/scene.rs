@@ -0,0 +1,601 @@
+//! This module describes scenes as plain data, so they can be saved to and loaded from files instead of being built in code.
+//! A [`SceneDescription`] holds the camera settings, the sky, the integrator, a list of named materials, and the objects in the scene, which refer to materials by name.
+//!
+//! With the `serde` feature enabled, every description type can be serialized with serde, and [`load_scene`] and [`save_scene`] read and write scenes as JSON.
+//! # Example
+//! A scene file looks like this:
+//! ```json
+//! {
+//!   "camera": { "image_width": 400, "image_height": 225, "lookfrom": [0, 0, 1] },
+//!   "sky": { "type": "gradient", "start": [0.5, 0.7, 1.0], "end": [1, 1, 1] },
+//!   "integrator": { "type": "path" },
+//!   "materials": {
+//!     "ground": { "type": "lambertian", "albedo": { "type": "checker", "scale": 0.5, "even": { "type": "solid", "color": [0.2, 0.3, 0.1] }, "odd": { "type": "solid", "color": [0.9, 0.9, 0.9] } } },
+//!     "glass": { "type": "dielectric", "ior": 1.5 }
+//!   },
+//!   "objects": [
+//!     { "type": "plane", "origin": [0, -0.5, 0], "normal": [0, 1, 0], "material": "ground" },
+//!     { "type": "sphere", "center": [0, 0, -1], "radius": 0.5, "material": "glass" }
+//!   ]
+//! }
+//! ```
+//! Any field of the camera that is left out uses its default value.
+//!
+//! Built in objects, materials, textures, skies and integrators know how to describe themselves, custom ones can too by implementing `describe` on their traits.
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Display},
+    io,
+    sync::Arc,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{Camera, GradientSky, Sky},
+    color::{Color, TransferFunction},
+    film::Filter,
+    hittable::{
+        bvh::BvhNode,
+        mesh::{Mesh, MeshData},
+        plane::Plane,
+        sphere::Sphere,
+        triangle::Triangle,
+        Hittable, HittableList,
+    },
+    integrator::{AmbientOcclusion, DebugIntegrator, DebugMode, Integrator, PathIntegrator},
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal, Normal},
+    obj::{self, ObjError},
+    sampler::SamplerKind,
+    stable_hash,
+    texture::{CheckerTexture, ImageTexture, NoiseTexture, Texture},
+    tonemap::ToneMap,
+    vec3::*,
+};
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+/// A full scene: the camera, the sky, the integrator, the materials, and the objects.
+pub struct SceneDescription {
+    /// The camera settings
+    pub camera: CameraDescription,
+    /// The sky, used for the background and ambient light
+    pub sky: SkyDescription,
+    /// The integrator, the algorithm used to light the scene
+    pub integrator: IntegratorDescription,
+    /// The materials used by the objects, by name
+    pub materials: BTreeMap<String, MaterialDescription>,
+    /// The objects in the scene
+    pub objects: Vec<ObjectDescription>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+/// The settings of a [`Camera`], see its fields for what each one does.
+#[allow(missing_docs)]
+pub struct CameraDescription {
+    pub image_width: u32,
+    pub image_height: u32,
+    pub samples: u32,
+    pub bounces: u32,
+    pub vfov: f64,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    pub defocus_angle: f64,
+    pub focus_dist: f64,
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    pub transfer: TransferFunction,
+    pub filter: Filter,
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    pub noise_threshold: f64,
+    pub min_samples: u32,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+/// A [`Sky`], either a solid color or a [`GradientSky`].
+#[allow(missing_docs)]
+pub enum SkyDescription {
+    Solid { color: Color },
+    Gradient { start: Color, end: Color },
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+/// An [`Integrator`], matching the integrators in the [`integrator`](crate::integrator) module.
+#[allow(missing_docs)]
+pub enum IntegratorDescription {
+    #[default]
+    Path,
+    AmbientOcclusion {
+        distance: f64,
+    },
+    Debug {
+        mode: DebugMode,
+    },
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+/// A [`Texture`], matching the textures in the [`texture`](crate::texture) module.
+#[allow(missing_docs)]
+pub enum TextureDescription {
+    Solid {
+        color: Color,
+    },
+    Checker {
+        scale: f64,
+        even: Box<TextureDescription>,
+        odd: Box<TextureDescription>,
+    },
+    /// A PPM image, relative paths are relative to the working directory
+    Image {
+        path: String,
+    },
+    Noise {
+        scale: f64,
+    },
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+/// A [`Material`], matching the materials in the [`material`](crate::material) module.
+#[allow(missing_docs)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureDescription,
+    },
+    Metal {
+        albedo: TextureDescription,
+        fuzz: f64,
+    },
+    Dielectric {
+        ior: f64,
+    },
+    DiffuseLight {
+        emit: TextureDescription,
+    },
+    Normal,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+/// An object in the scene, objects refer to materials by their name in [`SceneDescription::materials`].
+#[allow(missing_docs)]
+pub enum ObjectDescription {
+    Sphere {
+        center: Point3,
+        radius: f64,
+        material: String,
+    },
+    Plane {
+        origin: Point3,
+        normal: Vec3,
+        material: String,
+    },
+    Triangle {
+        vertices: [Point3; 3],
+        normals: Option<[Vec3; 3]>,
+        uvs: Option<[(f64, f64); 3]>,
+        material: String,
+    },
+    Mesh {
+        positions: Vec<Point3>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        normals: Vec<Vec3>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        uvs: Vec<(f64, f64)>,
+        indices: Vec<[usize; 3]>,
+        material: String,
+    },
+    /// A Wavefront OBJ model, using the materials from its own MTL files. Only used for loading, as models are saved as meshes
+    Obj { path: String },
+    /// A nested list of objects
+    List { objects: Vec<ObjectDescription> },
+    /// A list of objects sorted into a [`BvhNode`]
+    Bvh { objects: Vec<ObjectDescription> },
+}
+
+#[derive(Debug)]
+/// An error from building, loading or saving a scene.
+pub enum SceneError {
+    /// A file couldn't be read or written.
+    Io(io::Error),
+    /// An OBJ model in the scene couldn't be loaded.
+    Obj(ObjError),
+    /// The scene file isn't valid JSON, or doesn't match the scene format.
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+    /// An object refers to a material that isn't in the scene.
+    UnknownMaterial(String),
+    /// Something in the scene can't be described, like a custom object that doesn't implement `describe`.
+    Unsupported(String),
+    /// A mesh's buffers don't fit together, like an index past the last vertex.
+    InvalidMesh(String),
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{}", err),
+            SceneError::Obj(err) => write!(f, "failed to load model: {}", err),
+            #[cfg(feature = "serde")]
+            SceneError::Json(err) => write!(f, "invalid scene file: {}", err),
+            SceneError::UnknownMaterial(name) => write!(f, "unknown material '{}'", name),
+            SceneError::Unsupported(what) => write!(f, "can't describe {}", what),
+            SceneError::InvalidMesh(problem) => write!(f, "invalid mesh: {}", problem),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(err) => Some(err),
+            SceneError::Obj(err) => Some(err),
+            #[cfg(feature = "serde")]
+            SceneError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl From<ObjError> for SceneError {
+    fn from(err: ObjError) -> Self {
+        SceneError::Obj(err)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for SceneError {
+    fn from(err: serde_json::Error) -> Self {
+        SceneError::Json(err)
+    }
+}
+
+/// Gives names to the materials used by objects while a scene is being described, so objects sharing a material share a name.
+#[derive(Default)]
+pub struct MaterialTable {
+    names: Vec<(usize, String)>,
+    materials: BTreeMap<String, MaterialDescription>,
+}
+
+impl MaterialTable {
+    /// Returns the name of a material, describing it the first time it's seen.
+    /// Returns `None` if the material can't be described.
+    pub fn name(&mut self, mat: &Arc<dyn Material>) -> Option<String> {
+        let address = Arc::as_ptr(mat) as *const () as usize;
+        if let Some((_, name)) = self.names.iter().find(|(a, _)| *a == address) {
+            return Some(name.clone());
+        }
+        let name = format!("material{}", self.names.len());
+        self.materials.insert(name.clone(), mat.describe()?);
+        self.names.push((address, name.clone()));
+        Some(name)
+    }
+}
+
+impl Default for CameraDescription {
+    fn default() -> Self {
+        CameraDescription::from(&Camera::default())
+    }
+}
+
+impl From<&Camera> for CameraDescription {
+    fn from(cam: &Camera) -> Self {
+        CameraDescription {
+            image_width: cam.image_width,
+            image_height: cam.image_height,
+            samples: cam.samples,
+            bounces: cam.bounces,
+            vfov: cam.vfov,
+            lookfrom: cam.lookfrom,
+            lookat: cam.lookat,
+            vup: cam.vup,
+            defocus_angle: cam.defocus_angle,
+            focus_dist: cam.focus_dist,
+            exposure: cam.exposure,
+            tone_map: cam.tone_map,
+            transfer: cam.transfer,
+            filter: cam.filter,
+            seed: cam.seed,
+            sampler: cam.sampler,
+            noise_threshold: cam.noise_threshold,
+            min_samples: cam.min_samples,
+        }
+    }
+}
+
+impl CameraDescription {
+    /// Copies these settings onto a camera.
+    pub fn apply(&self, cam: &mut Camera) {
+        cam.image_width = self.image_width;
+        cam.image_height = self.image_height;
+        cam.samples = self.samples;
+        cam.bounces = self.bounces;
+        cam.vfov = self.vfov;
+        cam.lookfrom = self.lookfrom;
+        cam.lookat = self.lookat;
+        cam.vup = self.vup;
+        cam.defocus_angle = self.defocus_angle;
+        cam.focus_dist = self.focus_dist;
+        cam.exposure = self.exposure;
+        cam.tone_map = self.tone_map;
+        cam.transfer = self.transfer;
+        cam.filter = self.filter;
+        cam.seed = self.seed;
+        cam.sampler = self.sampler;
+        cam.noise_threshold = self.noise_threshold;
+        cam.min_samples = self.min_samples;
+    }
+}
+
+impl Default for SkyDescription {
+    fn default() -> Self {
+        SkyDescription::Gradient {
+            start: Color::new(0.5, 0.7, 1.0),
+            end: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl SkyDescription {
+    /// Creates the sky this describes.
+    pub fn build(&self) -> Box<dyn Sky> {
+        match self {
+            SkyDescription::Solid { color } => Box::new(*color),
+            SkyDescription::Gradient { start, end } => Box::new(GradientSky {
+                start: *start,
+                end: *end,
+            }),
+        }
+    }
+}
+
+impl IntegratorDescription {
+    /// Creates the integrator this describes.
+    pub fn build(&self) -> Box<dyn Integrator> {
+        match self {
+            IntegratorDescription::Path => Box::new(PathIntegrator),
+            IntegratorDescription::AmbientOcclusion { distance } => {
+                Box::new(AmbientOcclusion::new(*distance))
+            }
+            IntegratorDescription::Debug { mode } => Box::new(DebugIntegrator::new(*mode)),
+        }
+    }
+}
+
+impl TextureDescription {
+    /// Creates the texture this describes, loading images from disk.
+    pub fn build(&self) -> Result<Arc<dyn Texture>, SceneError> {
+        Ok(match self {
+            TextureDescription::Solid { color } => Arc::new(*color),
+            TextureDescription::Checker { scale, even, odd } => {
+                Arc::new(CheckerTexture::new(*scale, even.build()?, odd.build()?))
+            }
+            TextureDescription::Image { path } => Arc::new(ImageTexture::load_ppm(path)?),
+            TextureDescription::Noise { scale } => Arc::new(NoiseTexture::new(*scale)),
+        })
+    }
+}
+
+impl MaterialDescription {
+    /// Creates the material this describes.
+    pub fn build(&self) -> Result<Arc<dyn Material>, SceneError> {
+        Ok(match self {
+            MaterialDescription::Lambertian { albedo } => {
+                Arc::new(Lambertian::from_texture(albedo.build()?))
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                Arc::new(Metal::from_texture(albedo.build()?, *fuzz))
+            }
+            MaterialDescription::Dielectric { ior } => Arc::new(Dielectric::new(*ior)),
+            MaterialDescription::DiffuseLight { emit } => {
+                Arc::new(DiffuseLight::from_texture(emit.build()?))
+            }
+            MaterialDescription::Normal => Arc::new(Normal::new()),
+        })
+    }
+}
+
+impl ObjectDescription {
+    /// Creates the object this describes, looking up its material in `materials`.
+    pub fn build(
+        &self,
+        materials: &BTreeMap<String, Arc<dyn Material>>,
+    ) -> Result<Box<dyn Hittable>, SceneError> {
+        let material = |name: &String| {
+            materials
+                .get(name)
+                .cloned()
+                .ok_or_else(|| SceneError::UnknownMaterial(name.clone()))
+        };
+        let list = |objects: &Vec<ObjectDescription>| -> Result<HittableList, SceneError> {
+            let mut list = HittableList::new();
+            for object in objects {
+                list.add_boxed(object.build(materials)?);
+            }
+            Ok(list)
+        };
+
+        Ok(match self {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material: name,
+            } => Box::new(Sphere::new(*center, *radius, material(name)?)),
+            ObjectDescription::Plane {
+                origin,
+                normal,
+                material: name,
+            } => Box::new(Plane::new(*origin, *normal, material(name)?)),
+            ObjectDescription::Triangle {
+                vertices,
+                normals,
+                uvs,
+                material: name,
+            } => {
+                let [a, b, c] = *vertices;
+                let mut triangle = Triangle::new(a, b, c, material(name)?);
+                if let Some(normals) = normals {
+                    triangle = triangle.with_normals(*normals);
+                }
+                if let Some(uvs) = uvs {
+                    triangle = triangle.with_uvs(*uvs);
+                }
+                Box::new(triangle)
+            }
+            ObjectDescription::Mesh {
+                positions,
+                normals,
+                uvs,
+                indices,
+                material: name,
+            } => {
+                let data = MeshData {
+                    positions: positions.clone(),
+                    normals: normals.clone(),
+                    uvs: uvs.clone(),
+                    indices: indices.clone(),
+                };
+                data.validate().map_err(SceneError::InvalidMesh)?;
+                Box::new(Mesh::new(data, material(name)?))
+            }
+            ObjectDescription::Obj { path } => Box::new(obj::load_obj(path)?),
+            ObjectDescription::List { objects } => Box::new(list(objects)?),
+            ObjectDescription::Bvh { objects } => Box::new(BvhNode::new(list(objects)?)),
+        })
+    }
+}
+
+impl SceneDescription {
+    /// Describes a camera and scene.
+    /// Returns [`SceneError::Unsupported`] if the sky, or any object or material can't be described.
+    pub fn from_scene(cam: &Camera, world: &HittableList) -> Result<Self, SceneError> {
+        let (materials, objects) = describe_objects(world)?;
+        Ok(SceneDescription {
+            camera: CameraDescription::from(cam),
+            sky: cam
+                .sky
+                .describe()
+                .ok_or_else(|| SceneError::Unsupported("the sky".to_string()))?,
+            integrator: cam
+                .integrator
+                .describe()
+                .ok_or_else(|| SceneError::Unsupported("the integrator".to_string()))?,
+            materials,
+            objects,
+        })
+    }
+    /// Creates the camera and scene this describes. The camera is initialized, and ready to render.
+    pub fn build(&self) -> Result<(Camera, HittableList), SceneError> {
+        let mut cam = Camera::new();
+        self.camera.apply(&mut cam);
+        cam.sky = self.sky.build();
+        cam.integrator = self.integrator.build();
+        cam.initialize();
+
+        let mut materials = BTreeMap::new();
+        for (name, material) in &self.materials {
+            materials.insert(name.clone(), material.build()?);
+        }
+        let mut world = HittableList::new();
+        for object in &self.objects {
+            world.add_boxed(object.build(&materials)?);
+        }
+        Ok((cam, world))
+    }
+    /// Returns a hash of everything that decides what the samples of a render are, used by checkpoints to tell if the scene changed.
+    /// The sample count, adaptive sampling and output settings ( exposure, tone map and transfer function ) are left out, since a render can be resumed with different ones.
+    /// Image textures and OBJ models are hashed by the contents of their files, so editing them changes the hash too. The hash is the same on every machine and every run.
+    pub fn film_hash(&self) -> u64 {
+        stable_hash::view_hash(
+            &self.camera,
+            &self.sky,
+            &self.integrator,
+            stable_hash::objects_hash(&self.materials, &self.objects),
+        )
+    }
+    /// Parses a scene from JSON.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, SceneError> {
+        Ok(serde_json::from_str(json)?)
+    }
+    /// Writes the scene as pretty printed JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, SceneError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Describes the objects in `world`, and the materials they use.
+pub(crate) fn describe_objects(
+    world: &HittableList,
+) -> Result<
+    (
+        BTreeMap<String, MaterialDescription>,
+        Vec<ObjectDescription>,
+    ),
+    SceneError,
+> {
+    let mut materials = MaterialTable::default();
+    let mut objects = Vec::new();
+    for object in world.objects() {
+        objects.push(
+            object
+                .describe(&mut materials)
+                .ok_or_else(|| SceneError::Unsupported(object.as_string()))?,
+        );
+    }
+    Ok((materials.materials, objects))
+}
+
+/// Loads a JSON scene file, and returns its camera and objects.
+#[cfg(feature = "serde")]
+pub fn load_scene<P: AsRef<std::path::Path>>(
+    path: P,
+) -> Result<(Camera, HittableList), SceneError> {
+    SceneDescription::from_json(&std::fs::read_to_string(path)?)?.build()
+}
+
+/// Saves a camera and scene to a JSON scene file.
+#[cfg(feature = "serde")]
+pub fn save_scene<P: AsRef<std::path::Path>>(
+    path: P,
+    cam: &Camera,
+    world: &HittableList,
+) -> Result<(), SceneError> {
+    std::fs::write(path, SceneDescription::from_scene(cam, world)?.to_json()?)?;
+    Ok(())
+}
@@ -0,0 +1,430 @@
+//! This module hashes scene descriptions into hashes that are the same on every machine and every run, so they can be saved in files, like the scene hash of a [`Checkpoint`](crate::checkpoint::Checkpoint).
+//! Values are fed to the hasher field by field with a fixed byte encoding, instead of through `Debug` or std's `Hash`, which can change between Rust versions.
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{
+    camera::Camera,
+    film::Filter,
+    hittable::HittableList,
+    sampler::SamplerKind,
+    scene::{
+        describe_objects, CameraDescription, IntegratorDescription, MaterialDescription,
+        ObjectDescription, SkyDescription, TextureDescription,
+    },
+    vec3::Vec3,
+};
+
+/// A 64 bit FNV-1a hasher. Unlike std's hashers it's guaranteed to stay the same between Rust versions, so its hashes can be saved in files.
+pub(crate) struct StableHasher(u64);
+
+impl StableHasher {
+    pub(crate) fn new() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Feeds a value to a [`StableHasher`] field by field, with a fixed byte encoding, so the hash is the same on every machine.
+/// Enum variants are written as their index, followed by their fields.
+pub(crate) trait StableHash {
+    fn stable_hash(&self, h: &mut StableHasher);
+}
+
+/// Hashes a single value with a new [`StableHasher`].
+pub(crate) fn stable_hash<T: StableHash + ?Sized>(value: &T) -> u64 {
+    let mut h = StableHasher::new();
+    value.stable_hash(&mut h);
+    h.finish()
+}
+
+impl StableHash for u8 {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        h.write(&[*self]);
+    }
+}
+
+impl StableHash for u32 {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        h.write(&self.to_le_bytes());
+    }
+}
+
+impl StableHash for u64 {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        h.write(&self.to_le_bytes());
+    }
+}
+
+impl StableHash for usize {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        (*self as u64).stable_hash(h);
+    }
+}
+
+impl StableHash for f64 {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.to_bits().stable_hash(h);
+    }
+}
+
+impl StableHash for str {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.len().stable_hash(h);
+        h.write(self.as_bytes());
+    }
+}
+
+impl StableHash for String {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.as_str().stable_hash(h);
+    }
+}
+
+impl StableHash for Vec3 {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.x.stable_hash(h);
+        self.y.stable_hash(h);
+        self.z.stable_hash(h);
+    }
+}
+
+impl<A: StableHash, B: StableHash> StableHash for (A, B) {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.0.stable_hash(h);
+        self.1.stable_hash(h);
+    }
+}
+
+impl<T: StableHash, const N: usize> StableHash for [T; N] {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        for value in self {
+            value.stable_hash(h);
+        }
+    }
+}
+
+impl<T: StableHash> StableHash for [T] {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.len().stable_hash(h);
+        for value in self {
+            value.stable_hash(h);
+        }
+    }
+}
+
+impl<T: StableHash> StableHash for Vec<T> {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.as_slice().stable_hash(h);
+    }
+}
+
+impl<T: StableHash> StableHash for Option<T> {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        match self {
+            None => 0u8.stable_hash(h),
+            Some(value) => {
+                1u8.stable_hash(h);
+                value.stable_hash(h);
+            }
+        }
+    }
+}
+
+impl<T: StableHash> StableHash for Box<T> {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.as_ref().stable_hash(h);
+    }
+}
+
+impl StableHash for TextureDescription {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        match self {
+            TextureDescription::Solid { color } => {
+                0u8.stable_hash(h);
+                color.stable_hash(h);
+            }
+            TextureDescription::Checker { scale, even, odd } => {
+                1u8.stable_hash(h);
+                scale.stable_hash(h);
+                even.stable_hash(h);
+                odd.stable_hash(h);
+            }
+            TextureDescription::Image { path } => {
+                2u8.stable_hash(h);
+                path.stable_hash(h);
+            }
+            TextureDescription::Noise { scale } => {
+                3u8.stable_hash(h);
+                scale.stable_hash(h);
+            }
+        }
+    }
+}
+
+impl StableHash for MaterialDescription {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        match self {
+            MaterialDescription::Lambertian { albedo } => {
+                0u8.stable_hash(h);
+                albedo.stable_hash(h);
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                1u8.stable_hash(h);
+                albedo.stable_hash(h);
+                fuzz.stable_hash(h);
+            }
+            MaterialDescription::Dielectric { ior } => {
+                2u8.stable_hash(h);
+                ior.stable_hash(h);
+            }
+            MaterialDescription::DiffuseLight { emit } => {
+                3u8.stable_hash(h);
+                emit.stable_hash(h);
+            }
+            MaterialDescription::Normal => 4u8.stable_hash(h),
+        }
+    }
+}
+
+impl StableHash for Filter {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.tag().stable_hash(h);
+        self.radius().stable_hash(h);
+    }
+}
+
+impl StableHash for SamplerKind {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        self.tag().stable_hash(h);
+    }
+}
+
+impl StableHash for SkyDescription {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        match self {
+            SkyDescription::Solid { color } => {
+                0u8.stable_hash(h);
+                color.stable_hash(h);
+            }
+            SkyDescription::Gradient { start, end } => {
+                1u8.stable_hash(h);
+                start.stable_hash(h);
+                end.stable_hash(h);
+            }
+        }
+    }
+}
+
+impl StableHash for IntegratorDescription {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        match self {
+            IntegratorDescription::Path => 0u8.stable_hash(h),
+            IntegratorDescription::AmbientOcclusion { distance } => {
+                1u8.stable_hash(h);
+                distance.stable_hash(h);
+            }
+            IntegratorDescription::Debug { mode } => {
+                2u8.stable_hash(h);
+                mode.tag().stable_hash(h);
+            }
+        }
+    }
+}
+
+impl StableHash for ObjectDescription {
+    fn stable_hash(&self, h: &mut StableHasher) {
+        match self {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                0u8.stable_hash(h);
+                center.stable_hash(h);
+                radius.stable_hash(h);
+                material.stable_hash(h);
+            }
+            ObjectDescription::Plane {
+                origin,
+                normal,
+                material,
+            } => {
+                1u8.stable_hash(h);
+                origin.stable_hash(h);
+                normal.stable_hash(h);
+                material.stable_hash(h);
+            }
+            ObjectDescription::Triangle {
+                vertices,
+                normals,
+                uvs,
+                material,
+            } => {
+                2u8.stable_hash(h);
+                vertices.stable_hash(h);
+                normals.stable_hash(h);
+                uvs.stable_hash(h);
+                material.stable_hash(h);
+            }
+            ObjectDescription::Mesh {
+                positions,
+                normals,
+                uvs,
+                indices,
+                material,
+            } => {
+                3u8.stable_hash(h);
+                positions.stable_hash(h);
+                normals.stable_hash(h);
+                uvs.stable_hash(h);
+                indices.stable_hash(h);
+                material.stable_hash(h);
+            }
+            ObjectDescription::Obj { path } => {
+                4u8.stable_hash(h);
+                path.stable_hash(h);
+            }
+            ObjectDescription::List { objects } => {
+                5u8.stable_hash(h);
+                objects.stable_hash(h);
+            }
+            ObjectDescription::Bvh { objects } => {
+                6u8.stable_hash(h);
+                objects.stable_hash(h);
+            }
+        }
+    }
+}
+
+/// Returns the part of `film_hash` that comes from the objects in `world`, or `None` if they can't be described.
+/// Describing a big scene is slow, so the progressive renderer only does it once, and combines it with each camera with [`camera_film_hash`].
+pub(crate) fn world_film_hash(world: &HittableList) -> Option<u64> {
+    let (materials, objects) = describe_objects(world).ok()?;
+    Some(objects_hash(&materials, &objects))
+}
+
+/// Returns the `film_hash` of a scene with `cam`, and objects hashing to `objects`, or `None` if the sky or integrator can't be described.
+pub(crate) fn camera_film_hash(cam: &Camera, objects: u64) -> Option<u64> {
+    Some(view_hash(
+        &CameraDescription::from(cam),
+        &cam.sky.describe()?,
+        &cam.integrator.describe()?,
+        objects,
+    ))
+}
+
+/// Hashes the camera settings that change the samples, the sky and the integrator, along with the hash of the objects.
+pub(crate) fn view_hash(
+    camera: &CameraDescription,
+    sky: &SkyDescription,
+    integrator: &IntegratorDescription,
+    objects: u64,
+) -> u64 {
+    let mut h = StableHasher::new();
+    camera.image_width.stable_hash(&mut h);
+    camera.image_height.stable_hash(&mut h);
+    camera.bounces.stable_hash(&mut h);
+    camera.vfov.stable_hash(&mut h);
+    camera.lookfrom.stable_hash(&mut h);
+    camera.lookat.stable_hash(&mut h);
+    camera.vup.stable_hash(&mut h);
+    camera.defocus_angle.stable_hash(&mut h);
+    camera.focus_dist.stable_hash(&mut h);
+    camera.filter.stable_hash(&mut h);
+    camera.seed.stable_hash(&mut h);
+    camera.sampler.stable_hash(&mut h);
+    sky.stable_hash(&mut h);
+    integrator.stable_hash(&mut h);
+    objects.stable_hash(&mut h);
+    h.finish()
+}
+
+/// Hashes the materials and objects of a scene, along with the contents of the files they load.
+pub(crate) fn objects_hash(
+    materials: &BTreeMap<String, MaterialDescription>,
+    objects: &[ObjectDescription],
+) -> u64 {
+    let mut h = StableHasher::new();
+    materials.len().stable_hash(&mut h);
+    for (name, material) in materials {
+        name.stable_hash(&mut h);
+        material.stable_hash(&mut h);
+        match material {
+            MaterialDescription::Lambertian { albedo: texture }
+            | MaterialDescription::Metal {
+                albedo: texture, ..
+            }
+            | MaterialDescription::DiffuseLight { emit: texture } => {
+                hash_texture_files(texture, &mut h)
+            }
+            MaterialDescription::Dielectric { .. } | MaterialDescription::Normal => {}
+        }
+    }
+    objects.stable_hash(&mut h);
+    for object in objects {
+        hash_object_files(object, &mut h);
+    }
+    h.finish()
+}
+
+/// Hashes the images a texture loads.
+fn hash_texture_files(texture: &TextureDescription, h: &mut StableHasher) {
+    match texture {
+        TextureDescription::Image { path } => {
+            hash_file(Path::new(path), h);
+        }
+        TextureDescription::Checker { even, odd, .. } => {
+            hash_texture_files(even, h);
+            hash_texture_files(odd, h);
+        }
+        TextureDescription::Solid { .. } | TextureDescription::Noise { .. } => {}
+    }
+}
+
+/// Hashes the OBJ models an object loads, along with their MTL files, found the same way as [`load_obj`](crate::obj::load_obj).
+fn hash_object_files(object: &ObjectDescription, h: &mut StableHasher) {
+    match object {
+        ObjectDescription::Obj { path } => {
+            let path = Path::new(path);
+            let source = hash_file(path, h);
+            let dir = path.parent().unwrap_or(Path::new(""));
+            for line in String::from_utf8_lossy(&source).lines() {
+                let mut tokens = line.split_whitespace();
+                if tokens.next() == Some("mtllib") {
+                    for file in tokens {
+                        hash_file(&dir.join(file), h);
+                    }
+                }
+            }
+        }
+        ObjectDescription::List { objects } | ObjectDescription::Bvh { objects } => {
+            for object in objects {
+                hash_object_files(object, h);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Hashes the contents of a file, and returns them. Files that can't be read are hashed as missing, and return nothing.
+fn hash_file(path: &Path, h: &mut StableHasher) -> Vec<u8> {
+    match fs::read(path) {
+        Ok(bytes) => {
+            1u8.stable_hash(h);
+            bytes.len().stable_hash(h);
+            h.write(&bytes);
+            bytes
+        }
+        Err(_) => {
+            0u8.stable_hash(h);
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! `texture` is a collection of types that implement the `Texture` trait.
+//! Textures give a material a color that changes across the surface of an object, they're looked up with the `u`/`v` surface coordinates and hit point stored in a [`HitRecord`](crate::hittable::HitRecord).
+//! The available textures are:
+//! - [`Color`]: A solid color, the same everywhere.
+//! - [`CheckerTexture`]: A 3D checkerboard, alternating between two other textures.
+//! - [`ImageTexture`]: An image, wrapped around the object with its `u`/`v` coordinates.
+//! - [`NoiseTexture`]: Perlin noise, with a marble-like pattern.
+//!
+//! # Example
+//! ```
+//! use rtwlib::{color::Color, material::Lambertian, texture::CheckerTexture};
+//! use std::sync::Arc;
+//!
+//! let checker = Arc::new(CheckerTexture::from_colors(0.5, Color::new(0.2, 0.3, 0.1), Color::from(0.9)));
+//! let material = Arc::new(Lambertian::from_texture(checker));
+//! ```
+use std::{fmt::Debug, fs, io, path::Path, sync::Arc};
+
+use crate::{
+    color::Color,
+    sampler::{RandomSampler, Sampler},
+    scene::TextureDescription,
+    vec3::*,
+};
+
+/// A `Texture` is a trait that represents a color that varies across a surface.
+pub trait Texture: Debug + Send + Sync {
+    /// Returns the color of the texture at the surface coordinates `u`, `v` and hit point `p`.
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+    /// Describes the texture, so it can be saved in a scene file. Returns `None` if it can't be described.
+    fn describe(&self) -> Option<TextureDescription> {
+        None
+    }
+}
+
+/// A `Color` is a solid color texture, it has the same value everywhere.
+impl Texture for Color {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        *self
+    }
+    fn describe(&self) -> Option<TextureDescription> {
+        Some(TextureDescription::Solid { color: *self })
+    }
+}
+
+#[derive(Debug)]
+/// A 3D checkerboard texture, that alternates between the `even` and `odd` textures in cubes of size `scale`.
+/// The pattern is based on the hit point, not the `u`/`v` coordinates, so it looks the same on every kind of object.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    /// Creates a new `CheckerTexture` alternating between two textures, with squares of size `scale`.
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        CheckerTexture {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+    /// Creates a new `CheckerTexture` alternating between two solid colors, with squares of size `scale`.
+    pub fn from_colors(scale: f64, even: Color, odd: Color) -> Self {
+        CheckerTexture::new(scale, Arc::new(even), Arc::new(odd))
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x).floor() as i64;
+        let y = (self.inv_scale * p.y).floor() as i64;
+        let z = (self.inv_scale * p.z).floor() as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+    fn describe(&self) -> Option<TextureDescription> {
+        Some(TextureDescription::Checker {
+            scale: 1.0 / self.inv_scale,
+            even: Box::new(self.even.describe()?),
+            odd: Box::new(self.odd.describe()?),
+        })
+    }
+}
+
+#[derive(Debug)]
+/// An image texture, the image is stretched over the `u`/`v` coordinates of the object, and repeats outside of the 0-1 range.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    path: Option<String>,
+}
+
+impl ImageTexture {
+    /// Creates a new `ImageTexture` from a list of linear colors, in rows from top to bottom.
+    /// Panics if there aren't exactly `width * height` colors.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), width * height, "image size doesn't match");
+        ImageTexture {
+            width,
+            height,
+            pixels,
+            path: None,
+        }
+    }
+    /// Creates a new `ImageTexture` from sRGB encoded bytes, in the same `[R, G, B, R, G, B, ...]` layout as `Camera::render_to_bytes`.
+    pub fn from_rgb_bytes(width: usize, height: usize, bytes: &[u8]) -> Self {
+        let pixels = bytes
+            .chunks_exact(3)
+            .map(|rgb| Color::from_rgb_bytes([rgb[0], rgb[1], rgb[2]]))
+            .collect();
+        ImageTexture::new(width, height, pixels)
+    }
+    /// Loads an `ImageTexture` from a binary ( P6 ) or plain text ( P3 ) PPM file, with a max value of 255.
+    pub fn load_ppm<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let data = fs::read(&path)?;
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        // the header is 4 whitespace separated tokens: magic, width, height and max value
+        let mut tokens = Vec::new();
+        let mut pos = 0;
+        while tokens.len() < 4 {
+            while pos < data.len() && (data[pos].is_ascii_whitespace() || data[pos] == b'#') {
+                if data[pos] == b'#' {
+                    while pos < data.len() && data[pos] != b'\n' {
+                        pos += 1;
+                    }
+                }
+                pos += 1;
+            }
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(invalid("truncated PPM header"));
+            }
+            tokens.push(String::from_utf8_lossy(&data[start..pos]).to_string());
+        }
+        let parse = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| invalid("invalid PPM header"))
+        };
+        let (width, height) = (parse(&tokens[1])?, parse(&tokens[2])?);
+        if parse(&tokens[3])? != 255 {
+            return Err(invalid("only 8 bit PPM files are supported"));
+        }
+
+        let bytes = match tokens[0].as_str() {
+            "P6" => data
+                .get(pos + 1..pos + 1 + width * height * 3)
+                .map(<[u8]>::to_vec),
+            "P3" => String::from_utf8_lossy(&data[pos..])
+                .split_whitespace()
+                .take(width * height * 3)
+                .map(|s| s.parse::<u8>().ok())
+                .collect(),
+            _ => return Err(invalid("not a PPM file")),
+        };
+        match bytes {
+            Some(bytes) if bytes.len() == width * height * 3 => {
+                let mut texture = ImageTexture::from_rgb_bytes(width, height, &bytes);
+                texture.path = Some(path.as_ref().to_string_lossy().to_string());
+                Ok(texture)
+            }
+            _ => Err(invalid("PPM pixel data is truncated")),
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        if self.pixels.is_empty() {
+            return Color::new(0., 1., 1.); // obvious debug color for missing images
+        }
+        // wrap coordinates into 0-1, and flip v, since images are stored top to bottom
+        let u = u.rem_euclid(1.0);
+        let v = 1.0 - v.rem_euclid(1.0);
+
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i]
+    }
+    fn describe(&self) -> Option<TextureDescription> {
+        Some(TextureDescription::Image {
+            path: self.path.clone()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+/// A Perlin noise texture, with a marble-like pattern of stripes distorted by turbulence.
+/// Higher `scale` values make the pattern smaller.
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64,
+}
+
+impl NoiseTexture {
+    /// Creates a new `NoiseTexture` with the given scale.
+    pub fn new(scale: f64) -> Self {
+        NoiseTexture {
+            noise: Perlin::new(),
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        Color::from(0.5) * (1.0 + (self.scale * p.z + 10.0 * self.noise.turbulence(p, 7)).sin())
+    }
+    fn describe(&self) -> Option<TextureDescription> {
+        Some(TextureDescription::Noise { scale: self.scale })
+    }
+}
+
+const PERLIN_POINTS: usize = 256;
+const PERLIN_SEED: u64 = 0x5eed;
+
+#[derive(Debug)]
+/// Perlin noise generator, using random unit vectors at each lattice point.
+struct Perlin {
+    vectors: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    /// Creates the noise from a fixed seed, so the pattern is the same in every render.
+    fn new() -> Self {
+        let mut rng = RandomSampler::new(PERLIN_SEED);
+        let mut permutation = || {
+            // a Fisher-Yates shuffle
+            let mut p: Vec<usize> = (0..PERLIN_POINTS).collect();
+            for i in (1..p.len()).rev() {
+                let j = (rng.next_1d() * (i + 1) as f64) as usize;
+                p.swap(i, j);
+            }
+            p
+        };
+        let (perm_x, perm_y, perm_z) = (permutation(), permutation(), permutation());
+        Perlin {
+            vectors: (0..PERLIN_POINTS)
+                .map(|_| Vec3::random(-1., 1., &mut rng).normalized())
+                .collect(),
+            perm_x,
+            perm_y,
+            perm_z,
+        }
+    }
+
+    /// Returns smoothly interpolated noise at `p`, in the range -1 to 1.
+    fn noise(&self, p: &Point3) -> f64 {
+        let (u, v, w) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+        let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+        // hermite smoothing, to hide the grid
+        let (uu, vv, ww) = (
+            u * u * (3. - 2. * u),
+            v * v * (3. - 2. * v),
+            w * w * (3. - 2. * w),
+        );
+
+        let mask = PERLIN_POINTS as i64 - 1;
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let c = self.vectors[self.perm_x[((i + di) & mask) as usize]
+                        ^ self.perm_y[((j + dj) & mask) as usize]
+                        ^ self.perm_z[((k + dk) & mask) as usize]];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1. - fi) * (1. - uu))
+                        * (fj * vv + (1. - fj) * (1. - vv))
+                        * (fk * ww + (1. - fk) * (1. - ww))
+                        * dot(&c, &weight);
+                }
+            }
+        }
+        accum
+    }
+
+    /// Sums `depth` octaves of noise, each at double the frequency and half the weight of the last.
+    fn turbulence(&self, p: &Point3, depth: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = *p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accum += weight * self.noise(&temp_p);
+            weight *= 0.5;
+            temp_p = temp_p * 2;
+        }
+        accum.abs()
+    }
+}
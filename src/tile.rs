@@ -0,0 +1,120 @@
+//! This module contains the [`Tile`] struct, a rectangle of pixels in an image.
+//! The premade renderers split the image into tiles, and hand them out to the render threads one at a time. Tiles can also be rendered on their own with `Camera::render_region`, to re-render part of a big image, or to split a render between several machines.
+//!
+//! # Example
+//! ```
+//! use rtwlib::{camera::Camera, film::Film, hittable::HittableList};
+//!
+//! let mut cam = Camera::new();
+//! (cam.image_width, cam.image_height, cam.samples) = (40, 30, 2);
+//! let world = HittableList::new();
+//!
+//! // render each tile on its own ( this could be on different machines ), then put them together
+//! let mut image = Film::with_filter(cam.image_width, cam.image_height, cam.filter);
+//! for tile in cam.tiles() {
+//!     image.merge(&cam.render_region(&world, tile, |_| {}));
+//! }
+//! ```
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A rectangle of pixels, starting at pixel (`x`, `y`), where (0, 0) is the top left of the image.
+pub struct Tile {
+    /// The column of the left edge
+    pub x: u32,
+    /// The row of the top edge
+    pub y: u32,
+    /// The width in pixels
+    pub width: u32,
+    /// The height in pixels
+    pub height: u32,
+}
+
+impl Tile {
+    /// Creates a new `Tile`.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Tile {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    /// Creates a `Tile` from the corners of a rectangle, covering columns `x0..x1` and rows `y0..y1`.
+    pub fn from_corners(x0: u32, y0: u32, x1: u32, y1: u32) -> Self {
+        Tile::new(x0, y0, x1.saturating_sub(x0), y1.saturating_sub(y0))
+    }
+    /// Returns true if the tile has no pixels.
+    pub fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+    /// Returns true if pixel (`x`, `y`) is in the tile.
+    pub fn contains(self, x: u32, y: u32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+    /// Returns the part of the tile that is also in `other`, which is empty if they don't overlap.
+    pub fn intersect(self, other: Tile) -> Tile {
+        let (x0, y0) = (self.x.max(other.x), self.y.max(other.y));
+        let x1 = (self.x + self.width).min(other.x + other.width);
+        let y1 = (self.y + self.height).min(other.y + other.height);
+        Tile::from_corners(x0, y0, x1.max(x0), y1.max(y0))
+    }
+    /// Grows the tile by `pixels` on every side, without going past the edges of `bounds`.
+    pub fn expand(self, pixels: u32, bounds: Tile) -> Tile {
+        Tile::from_corners(
+            self.x.saturating_sub(pixels),
+            self.y.saturating_sub(pixels),
+            self.x + self.width + pixels,
+            self.y + self.height + pixels,
+        )
+        .intersect(bounds)
+    }
+    /// Splits the tile into smaller tiles of `size` by `size` pixels, in rows from top to bottom. The tiles on the right and bottom edges may be smaller.
+    pub fn split(self, size: u32) -> Tiles {
+        Tiles {
+            area: self,
+            size: size.max(1),
+            next: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// An iterator over the tiles covering an area, made with [`Tile::split`].
+pub struct Tiles {
+    area: Tile,
+    size: u32,
+    next: u32,
+}
+
+impl Tiles {
+    fn columns(&self) -> u32 {
+        self.area.width.div_ceil(self.size)
+    }
+    fn rows(&self) -> u32 {
+        self.area.height.div_ceil(self.size)
+    }
+}
+
+impl Iterator for Tiles {
+    type Item = Tile;
+
+    fn next(&mut self) -> Option<Tile> {
+        if self.next >= self.columns() * self.rows() {
+            return None;
+        }
+        let (column, row) = (self.next % self.columns(), self.next / self.columns());
+        self.next += 1;
+        let (x, y) = (
+            self.area.x + column * self.size,
+            self.area.y + row * self.size,
+        );
+        Some(Tile::from_corners(x, y, x + self.size, y + self.size).intersect(self.area))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = (self.columns() * self.rows()).saturating_sub(self.next) as usize;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Tiles {}
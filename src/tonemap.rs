@@ -0,0 +1,136 @@
+//! This module contains tone mapping operators, which squeeze the unbounded brightness of a render into the 0-1 range a display can show.
+//! Without tone mapping every channel above 1.0 is simply clipped, so bright lights and highlights turn into flat patches of color.
+//!
+//! Tone mapping is one step of the [`OutputTransform`], which turns linear colors into bytes:
+//!     1. The color is scaled by the exposure.
+//!     2. The tone map compresses it into the 0-1 range.
+//!     3. The transfer function encodes it, see [`TransferFunction`].
+//!
+//! # Example
+//! ```no_run
+//! use rtwlib::{camera::Camera, hittable::HittableList, tonemap::ToneMap};
+//!
+//! let mut cam = Camera::new();
+//! cam.exposure = 1.0; // twice as bright
+//! cam.tone_map = ToneMap::Aces;
+//! let buffer = cam.render_to_bytes(HittableList::new(), |_| {});
+//! ```
+use crate::color::{Color, TransferFunction};
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+/// A tone mapping operator, used to fit bright colors into the range a display can show.
+pub enum ToneMap {
+    /// No tone mapping, colors above 1.0 are clipped.
+    #[default]
+    None,
+    /// The classic Reinhard operator, `c / (1 + c)` on each channel. Never clips, but washes out bright areas a little.
+    Reinhard,
+    /// Reinhard with a white point, the brightness that maps to pure white. Brighter colors clip.
+    ReinhardExtended {
+        /// The brightness that is mapped to 1.0
+        white: f64,
+    },
+    /// A fit of the ACES filmic curve, with a contrasty, film-like look.
+    Aces,
+    /// The filmic curve from Uncharted 2 by John Hable, softer than ACES.
+    Hable,
+    /// An approximation of AgX, which desaturates very bright colors towards white, the way film does.
+    Agx,
+}
+
+impl ToneMap {
+    /// Tone maps a linear color, returning a linear color in the 0-1 range, ready to be encoded.
+    pub fn apply(self, color: Color) -> Color {
+        match self {
+            ToneMap::None => color,
+            ToneMap::Reinhard => map_channels(color, |c| c / (1.0 + c)),
+            ToneMap::ReinhardExtended { white } => {
+                map_channels(color, |c| c * (1.0 + c / (white * white)) / (1.0 + c))
+            }
+            ToneMap::Aces => map_channels(color, aces),
+            ToneMap::Hable => {
+                const WHITE: f64 = 11.2;
+                // the curve is made for exposures around 2x brighter than the render
+                map_channels(color, |c| hable(2.0 * c) / hable(WHITE))
+            }
+            ToneMap::Agx => agx(color),
+        }
+    }
+}
+
+/// Runs `curve` on each channel of a color, clamping the result to the 0-1 range.
+fn map_channels(color: Color, curve: impl Fn(f64) -> f64) -> Color {
+    let map = |c: f64| curve(c.max(0.0)).clamp(0.0, 1.0);
+    Color::new(map(color.x), map(color.y), map(color.z))
+}
+
+/// Krzysztof Narkowicz's fit of the ACES filmic curve.
+fn aces(x: f64) -> f64 {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    (x * (a * x + b)) / (x * (c * x + d) + e)
+}
+
+fn hable(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+/// A minimal AgX, with a polynomial fit of the default contrast curve.
+fn agx(color: Color) -> Color {
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    // move into the AgX working space, which is slightly inset from the primaries
+    let inset = Color::new(
+        0.842479 * color.x + 0.078434 * color.y + 0.079224 * color.z,
+        0.042328 * color.x + 0.878469 * color.y + 0.079166 * color.z,
+        0.042376 * color.x + 0.078434 * color.y + 0.879143 * color.z,
+    );
+    let curved = map_channels(inset, |c| {
+        let log = (c.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let (x2, x4) = (log * log, log * log * log * log);
+        15.5 * x4 * x2 - 40.14 * x4 * log + 31.96 * x4 - 6.868 * x2 * log
+            + 0.4298 * x2
+            + 0.1191 * log
+            - 0.00232
+    });
+    let outset = Color::new(
+        1.196879 * curved.x - 0.098021 * curved.y - 0.099030 * curved.z,
+        -0.052897 * curved.x + 1.151903 * curved.y - 0.098961 * curved.z,
+        -0.052972 * curved.x - 0.098043 * curved.y + 1.151074 * curved.z,
+    );
+    // the curve outputs display encoded values, so decode them back to linear
+    map_channels(outset, |c| c.powf(2.2))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Every step used to turn a linear render into 8 bit colors: exposure, tone mapping, and the transfer function.
+/// The camera builds one from its settings with `Camera::output_transform`.
+pub struct OutputTransform {
+    /// The exposure in stops, each stop doubles the brightness. 0.0 leaves the colors as they are.
+    pub exposure: f64,
+    /// The tone mapping operator
+    pub tone_map: ToneMap,
+    /// The curve used to encode the tone mapped colors
+    pub transfer: TransferFunction,
+}
+
+impl OutputTransform {
+    /// Applies the exposure and tone map to a linear color, and encodes it, returning a color in the 0-1 range.
+    pub fn apply(&self, color: Color) -> Color {
+        self.transfer.encode_color(self.tone_mapped(color))
+    }
+    /// Applies the transform to a linear color, and quantizes it to bytes.
+    pub fn to_rgb_bytes(&self, color: Color) -> [u8; 3] {
+        self.tone_mapped(color).to_rgb_bytes_with(self.transfer)
+    }
+
+    fn tone_mapped(&self, color: Color) -> Color {
+        self.tone_map.apply(color * 2f64.powf(self.exposure))
+    }
+}
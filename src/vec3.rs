@@ -1,12 +1,19 @@
 //! `Vec3` is a simple 3D vector struct, with x, y, and z components, and a bunch of utility functions.
 //! All of the vector math used in the raytracer is implemented here.
 //! Vec3 has a few aliases, such as `Point3`, which is used to represent a point in 3D space, and `Color`, which is used to represent a color, and these are exchangable. ( althoug I would reccomend using `Color` for colors, and `Point3` for discrete positions )
-use rand::Rng;
+use crate::sampler::Sampler;
+use std::f64::consts::PI;
 use std::ops::SubAssign;
 #[allow(dead_code)]
 use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, Neg, Sub};
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[f64; 3]", into = "[f64; 3]")
+)]
 ///A 3D vector struct, with x, y, and z components, and a bunch of utility functions.
+///With the `serde` feature, vectors are serialized as `[x, y, z]` arrays.
 pub struct Vec3 {
     ///The x component of the vector.
     pub x: f64,
@@ -31,14 +38,13 @@ impl Vec3 {
         }
     }
 
-    /// Returns a random vector in the bounds of `min` to `max`
+    /// Returns a random vector in the bounds of `min` to `max`, using numbers from `sampler`
     /// All components are individually randomised
-    pub fn random(min: f64, max: f64) -> Vec3 {
-        let mut rng = rand::thread_rng();
+    pub fn random(min: f64, max: f64, sampler: &mut dyn Sampler) -> Vec3 {
         Vec3 {
-            x: rng.gen_range(min..max),
-            y: rng.gen_range(min..max),
-            z: rng.gen_range(min..max),
+            x: sampler.gen_range(min, max),
+            y: sampler.gen_range(min, max),
+            z: sampler.gen_range(min, max),
         }
     }
     /// returns the length of a vector
@@ -59,38 +65,52 @@ impl Vec3 {
         //constrains vectors to the unit sphere (-1 to 1)
         return self / self.length();
     }
-    /// Returns a random vector within in the unit sphere
-    fn random_in_unit_sphere() -> Vec3 {
-        //gets a random normalized vector
-        loop {
-            let p = Vec3::random(-1., 1.);
-            if p.length_squared() < 1. {
-                return p;
-            }
-        }
-    }
     /// Returns a random vector in the unit disk ( x and y components only )
-    pub fn random_in_unit_disk() -> Vec3 {
-        loop {
-            let p = Vec3::random(-1., 1.) * Vec3::new(1., 1., 0.);
-            if p.length_squared() < 1. {
-                return p;
-            }
+    pub fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Vec3 {
+        // Shirley and Chiu's concentric mapping, which squeezes the square into the disk without tearing it,
+        // so samples spread evenly over the square stay spread evenly over the disk
+        let (x, y) = sampler.next_2d();
+        let (a, b) = (2. * x - 1., 2. * y - 1.);
+        if a == 0. && b == 0. {
+            return Vec3::from(0.);
         }
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, PI / 4. * (b / a))
+        } else {
+            (b, PI / 2. - PI / 4. * (a / b))
+        };
+        Vec3::new(r * theta.cos(), r * theta.sin(), 0.)
+    }
+    /// Returns two unit vectors at right angles to each other and to this one, which must be a unit vector.
+    /// Together they make a coordinate system around the vector, used to turn directions around the Z axis into directions around it.
+    pub fn orthonormal_basis(self) -> (Vec3, Vec3) {
+        // Duff et al.'s branchless basis, from "Building an Orthonormal Basis, Revisited"
+        let sign = 1f64.copysign(self.z);
+        let a = -1. / (sign + self.z);
+        let b = self.x * self.y * a;
+        (
+            Vec3::new(1. + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Vec3::new(b, sign + self.y * self.y * a, -self.y),
+        )
     }
     /// Reflects the vector across a surface normal
     pub fn reflect(self, n: &Vec3) -> Self {
         return self - *n * dot(&self, &n) * 2.;
     }
     /// Returns a random vector with length 1
-    pub fn random_normalized() -> Vec3 {
-        Self::random_in_unit_sphere().normalized()
+    pub fn random_normalized(sampler: &mut dyn Sampler) -> Vec3 {
+        // a uniform height and angle give a uniform point on the sphere
+        let (u, v) = sampler.next_2d();
+        let z = 1. - 2. * u;
+        let r = (1. - z * z).max(0.).sqrt();
+        let phi = 2. * PI * v;
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
     }
     /// Returns a random vector with length 1, on the hemisphere of a normal vector
     /// This means the vector will be facing outwards relative to the normal
-    pub fn random_on_hemisphere(normal: Vec3) -> Vec3 {
+    pub fn random_on_hemisphere(normal: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
         // a random vector facing outwards relative to the normal
-        let on_sphere = Self::random_normalized();
+        let on_sphere = Self::random_normalized(sampler);
         if dot(&on_sphere, &normal) > 0.0 {
             return on_sphere;
         } else {
@@ -148,6 +168,20 @@ impl From<f64> for Vec3 {
         Vec3 { x: n, y: n, z: n }
     }
 }
+impl From<[f64; 3]> for Vec3 {
+    fn from(v: [f64; 3]) -> Self {
+        Vec3 {
+            x: v[0],
+            y: v[1],
+            z: v[2],
+        }
+    }
+}
+impl From<Vec3> for [f64; 3] {
+    fn from(v: Vec3) -> Self {
+        [v.x, v.y, v.z]
+    }
+}
 //allows indexing the Vector with (unsigned) integer values, both mutable and immutable  eg. vec3[1] = vec3.y
 impl Index<usize> for Vec3 {
     type Output = f64;